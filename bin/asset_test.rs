@@ -1,19 +1,14 @@
 use ark_bn254::Fr;
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
-use ark_relations::r1cs::ConstraintSystem;
-use ark_std::{test_rng, UniformRand};
-use ducat::{
-    core::{network::Network, org::Organization, run_config::RUN_CONFIG, transaction::Transaction},
-    utils::generate_random_in_range,
+use ducat::core::{
+    key_derivation::MasterSeed, network::Network, org::Organization, run_config::RUN_CONFIG,
+    transaction_builder::TransactionBuilder,
 };
-use rand::{seq::IteratorRandom, Rng};
+use rand::{rngs::OsRng, Rng};
 use std::time::Instant;
 
 pub fn main() {
     std::env::set_var("RUST_BACKTRACE", "full");
-
-    let mut rng = test_rng();
-    let cs = ConstraintSystem::<Fr>::new_ref();
+    RUN_CONFIG.configure_thread_pool();
 
     // Create and add organizations to the network
     let mut network = Network::<Fr>::new();
@@ -25,14 +20,12 @@ pub fn main() {
     //       This may not necessarily be needed of we consider each run their oen epoch
     for i in 0..RUN_CONFIG.org_count {
         let org_name = format!("org{}", i + 1);
-        let unused_serial_numbers = Organization::create_unused_serial_numbers_list(&cs);
-
         let initial_balance = rand::thread_rng().gen_range(5..500000); // Random initial balance
         let addresses = Organization::create_known_addresses(
-            &cs,
+            &MasterSeed::random(&mut OsRng),
             RUN_CONFIG.addresses_per_organization,
-            i * RUN_CONFIG.addresses_per_organization,
         );
+        let unused_serial_numbers = Organization::create_unused_serial_numbers_list(&addresses);
         let organization = Organization::new(
             org_name.clone(),
             initial_balance,
@@ -45,47 +38,29 @@ pub fn main() {
 
     // Generate random transaction data
     for _ in 0..RUN_CONFIG.transaction_count {
-        let tid = FpVar::new_input(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
-
         // Select random sender and receiver organizations
         let sender_index = rand::thread_rng().gen_range(0..RUN_CONFIG.org_count);
+        // Sender and receiver may be the same organization, such transfers leave its delta alone
         let receiver_index = rand::thread_rng().gen_range(0..RUN_CONFIG.org_count);
 
-        // Ensure sender and receiver are different
-        let receiver_index = if sender_index == receiver_index {
-            (receiver_index + 1) % RUN_CONFIG.org_count
-        } else {
-            receiver_index
+        // Create the transaction, the builder picks the addresses, the serial number and the transaction id
+        let value = rand::thread_rng().gen_range(1..100);
+        let transaction = match TransactionBuilder::new(&network)
+            .from_organization(&organizations[sender_index].identifier())
+            .to_organization(&organizations[receiver_index].identifier())
+            .value(value)
+            .build()
+        {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                println!("Skipping transaction: {error}");
+                continue;
+            }
         };
 
-        // Select random addresses from the organizations
-        let sender_address = organizations[sender_index]
-            .known_addresses() // Returns a `&HashSet<String>`
-            .iter() // Create an iterator over the addresses
-            .choose(&mut rng)
-            .cloned()
-            .unwrap();
-        let receiver_address = organizations[receiver_index]
-            .known_addresses() // Returns a `&HashSet<String>`
-            .iter() // Create an iterator over the addresses
-            .choose(&mut rng)
-            .cloned()
-            .unwrap();
-        let sn_secret = FpVar::new_input(cs.clone(), || Ok(generate_random_in_range())).unwrap();
-
-        // Create the transaction
-        let value = rand::thread_rng().gen_range(0..100);
-        let transaction = Transaction::new(tid, value, sender_address, receiver_address, sn_secret);
-
-        // println!(
-        //     "Forwarding transaction of value: {} from sender: {:?} to receiver: {:?}",
-        //     transaction.value(),
-        //     transaction.sender_address(),
-        //     transaction.receiver_address()
-        // );
-
-        // Forward the transaction to the network
-        network.forward_transaction(transaction);
+        // Hand the receiver its opening and forward the shared transaction to the network
+        network.send_openings(&transaction);
+        network.forward_transaction(transaction.shared().clone());
     }
     let start = Instant::now();
     // network.dump_network_info();
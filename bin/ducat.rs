@@ -0,0 +1,387 @@
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{Proof, VerifyingKey};
+use ducat::{
+    core::{
+        block::Block,
+        bulletin_board::BulletinBoard,
+        disjointness::failed_disjointness,
+        membership::MembershipRecord,
+        network_state::NetworkState,
+        wire::{encode_hex, hex},
+    },
+    gadgets::batch_verifier::batch_verify_or_find_invalid,
+    verifier::{Auditor, EpochStatement},
+};
+use rand::rngs::OsRng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+const USAGE: &str = "Usage: ducat <command> [--state PATH] [options]
+
+Commands:
+  init       Create a network state and the verifying keys of its proofs
+               --orgs N (2) --addresses N (10) --balance N (1000) --fee N (0) --block-size N (100)
+               --keys PATH (ducat_keys.json)
+  simulate   Generate random transactions and append their blocks to the state
+               --transactions N (10)
+  prove      Post the current epoch's artifacts with epoch and asset proofs on top of the bulletin board published
+             so far, and publish it with the blocks and blockchain validator proofs with --validator
+               --org ID (every organization, repeatable) --board PATH (ducat_board.json)
+  end-epoch  Close the current epoch, later blocks belong to the next one
+  verify     Check the blocks, the bulletin board, the zero-sum of the posted delta commitments, the posted address
+             sets and the proofs, using only the verifying keys and what `prove` published
+               --keys PATH (ducat_keys.json) --board PATH (ducat_board.json)
+  inspect    Dump the organizations and the blockchain, every block with --blocks
+
+The state is read from and written to --state PATH (ducat_state.json) by every command but verify";
+
+/// The options each command takes, anything else is rejected
+fn known_options(command: &str) -> Option<&'static [&'static str]> {
+    match command {
+        "init" => Some(&[
+            "state",
+            "orgs",
+            "addresses",
+            "balance",
+            "fee",
+            "block-size",
+            "keys",
+        ]),
+        "simulate" => Some(&["state", "transactions"]),
+        "prove" => Some(&["state", "org", "board", "validator"]),
+        "end-epoch" => Some(&["state"]),
+        "verify" => Some(&["keys", "board"]),
+        "inspect" => Some(&["state", "blocks"]),
+        "help" | "--help" => Some(&[]),
+        _ => None,
+    }
+}
+
+/// The verifying keys `init` sets up along with the state. They are public, the proving keys stay in the state
+#[derive(Serialize, Deserialize)]
+struct VerifyingKeys {
+    #[serde(with = "hex")]
+    auditor: Auditor,
+    #[serde(with = "hex")]
+    validator_verifying_key: VerifyingKey<Bn254>,
+}
+
+/// What `prove` publishes for `verify`: the blocks, who joined and left the network, and the bulletin board with the
+/// postings of every epoch proven so far, plus the blockchain validator proofs of the selected organizations in the
+/// latest one
+#[derive(Serialize, Deserialize)]
+struct PublicRecord {
+    epoch: usize,
+    blocks: Vec<Block<Fr>>,
+    membership: Vec<MembershipRecord>,
+    board: BulletinBoard<Fr>,
+    #[serde(with = "hex")]
+    validator_proofs: Vec<(String, Proof<Bn254>)>,
+}
+
+/// Command line options, `--name value` pairs and bare `--flag`s
+struct Options {
+    values: HashMap<String, Vec<String>>,
+}
+impl Options {
+    /// Collect the options, failing on any that isn't in `known`
+    fn parse(arguments: &[String], known: &[&str]) -> Self {
+        let mut values: HashMap<String, Vec<String>> = HashMap::new();
+        let mut arguments = arguments.iter().peekable();
+        while let Some(argument) = arguments.next() {
+            let Some(name) = argument.strip_prefix("--") else {
+                fail(&format!("Unexpected argument {argument}"));
+            };
+            if !known.contains(&name) {
+                fail(&format!("Unknown option {argument}"));
+            }
+            let entry = values.entry(name.to_owned()).or_default();
+            if let Some(value) = arguments.next_if(|value| !value.starts_with("--")) {
+                entry.push(value.clone());
+            }
+        }
+        Self { values }
+    }
+    fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+    fn all(&self, name: &str) -> Vec<String> {
+        self.values.get(name).cloned().unwrap_or_default()
+    }
+    fn path(&self, name: &str, default: &str) -> PathBuf {
+        PathBuf::from(self.all(name).pop().unwrap_or_else(|| default.to_owned()))
+    }
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> T {
+        match self.all(name).pop() {
+            Some(value) => value
+                .parse()
+                .unwrap_or_else(|_| fail(&format!("--{name} expects a number, got {value}"))),
+            None => default,
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    exit(2)
+}
+
+fn load_state(options: &Options) -> NetworkState<Fr> {
+    let path = options.path("state", "ducat_state.json");
+    NetworkState::load(&path)
+        .unwrap_or_else(|error| fail(&format!("Couldn't load {}: {error}", path.display())))
+}
+
+fn save_state(options: &Options, state: &NetworkState<Fr>) {
+    let path = options.path("state", "ducat_state.json");
+    if let Err(error) = state.save(&path) {
+        eprintln!("Couldn't save {}: {error}", path.display());
+        exit(1);
+    }
+    println!("Saved state to {}", path.display());
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|error| error.to_string()))
+        .unwrap_or_else(|error| fail(&format!("Couldn't load {}: {error}", path.display())))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) {
+    let json = serde_json::to_string_pretty(value).unwrap();
+    if let Err(error) = fs::write(path, json) {
+        eprintln!("Couldn't save {}: {error}", path.display());
+        exit(1);
+    }
+}
+
+fn init(options: &Options) {
+    let block_size = options.number("block-size", 100);
+    if block_size == 0 {
+        fail("--block-size has to be at least 1");
+    }
+    let state = NetworkState::<Fr>::new(
+        options.number("orgs", 2),
+        options.number("addresses", 10),
+        options.number("balance", 1000),
+        options.number("fee", 0),
+        block_size,
+        &mut OsRng,
+    );
+    println!(
+        "Created a network of {} organizations",
+        state.organizations().len()
+    );
+    save_state(options, &state);
+    let path = options.path("keys", "ducat_keys.json");
+    write_json(
+        &path,
+        &VerifyingKeys {
+            auditor: state.auditor().clone(),
+            validator_verifying_key: state.validator_verifying_key().clone(),
+        },
+    );
+    println!("Saved verifying keys to {}", path.display());
+}
+
+fn simulate(options: &Options) {
+    let mut state = load_state(options);
+    let height = state.blocks().len();
+    let network = state.simulate(options.number("transactions", 10), &mut OsRng);
+    let transaction_count: usize = state.blocks()[height..].iter().map(Vec::len).sum();
+    println!(
+        "Applied {transaction_count} transactions in {} blocks, the blockchain is {} blocks high",
+        state.blocks().len() - height,
+        network.blockchain().height()
+    );
+    save_state(options, &state);
+}
+
+fn prove(options: &Options) {
+    let state = load_state(options);
+    let mut network = state.network();
+    // Earlier epochs' postings stay on the board, this epoch's go on top of them
+    let path = options.path("board", "ducat_board.json");
+    if path.exists() {
+        let published: PublicRecord = read_json(&path);
+        network.set_bulletin_board(published.board);
+    }
+    let mut identifiers = options.all("org");
+    if identifiers.is_empty() {
+        identifiers = state
+            .organizations()
+            .iter()
+            .map(|spec| spec.identifier().to_owned())
+            .collect();
+    }
+    if let Some(unknown) = identifiers
+        .iter()
+        .find(|identifier| network.organization(identifier).is_none())
+    {
+        fail(&format!("Unknown organization {unknown}"));
+    }
+
+    network.transfer_delta_to_organization_balance();
+    let submissions = network.prove_epochs(
+        &identifiers,
+        state.epoch_proving_key(),
+        state.asset_proving_key(),
+    );
+    println!(
+        "Generated epoch and asset proofs for {} assets",
+        submissions.len()
+    );
+    if let Err(error) = network.post_epoch_artifacts(&submissions) {
+        eprintln!("Couldn't post to the bulletin board: {error}");
+        exit(1);
+    }
+    let validator_proofs = if options.has("validator") {
+        let proofs =
+            network.prove_blockchain_validity_with(&identifiers, state.validator_proving_key());
+        println!("Generated {} blockchain validator proofs", proofs.len());
+        proofs
+    } else {
+        vec![]
+    };
+
+    let blockchain = network.blockchain();
+    let record = PublicRecord {
+        epoch: blockchain.epoch(),
+        blocks: blockchain.blocks().to_vec(),
+        membership: blockchain.membership_history().to_vec(),
+        board: network.bulletin_board().clone(),
+        validator_proofs,
+    };
+    write_json(&path, &record);
+    println!("Saved the bulletin board to {}", path.display());
+}
+
+fn verify(options: &Options) {
+    let keys: VerifyingKeys = read_json(&options.path("keys", "ducat_keys.json"));
+    let record: PublicRecord = read_json(&options.path("board", "ducat_board.json"));
+
+    let mut valid = true;
+    let mut check = |name: &str, passed: bool| {
+        println!("{name}: {}", if passed { "ok" } else { "FAILED" });
+        valid &= passed;
+    };
+    check("Block chain", Block::verify_chain(&record.blocks));
+    check(
+        "Bulletin board",
+        record.board.verify_chain(&record.membership),
+    );
+    let statement =
+        EpochStatement::from_bulletin_board(&record.board, &record.membership, record.epoch);
+    check("Zero-sum delta commitments", statement.is_zero_sum());
+    let address_sets = record.board.address_set_commitments(record.epoch);
+    let latest_block_hash = record.blocks.last().map_or([0; 32], |block| *block.hash());
+    check(
+        "Disjoint address sets",
+        address_sets
+            .iter()
+            .all(|(_, commitment)| *commitment.salt() == latest_block_hash)
+            && failed_disjointness(&address_sets).is_empty(),
+    );
+
+    // Only the organizations that posted proofs are audited
+    let submissions = record.board.submissions(record.epoch);
+    for verdict in keys
+        .auditor
+        .audit(&statement, &submissions)
+        .iter()
+        .filter(|verdict| {
+            submissions
+                .iter()
+                .any(|submission| submission.identifier() == verdict.identifier())
+        })
+    {
+        check(
+            &format!("Epoch and asset proofs of {}", verdict.identifier()),
+            verdict.is_valid(),
+        );
+        for failure in verdict.failures() {
+            println!("  {failure:?}");
+        }
+    }
+    let proofs: Vec<(Proof<Bn254>, Vec<Fr>)> = record
+        .validator_proofs
+        .iter()
+        .map(|(_, proof)| (proof.clone(), vec![]))
+        .collect();
+    let invalid = batch_verify_or_find_invalid(&keys.validator_verifying_key, &proofs);
+    for (index, (identifier, _)) in record.validator_proofs.iter().enumerate() {
+        check(
+            &format!("Blockchain validator proof of {identifier}"),
+            !invalid.contains(&index),
+        );
+    }
+    if !valid {
+        exit(1);
+    }
+}
+
+fn end_epoch(options: &Options) {
+    let mut state = load_state(options);
+    state.end_epoch();
+    println!(
+        "Ended epoch {} after {} blocks",
+        state.epoch() - 1,
+        state.blocks().len()
+    );
+    save_state(options, &state);
+}
+
+fn inspect(options: &Options) {
+    let state = load_state(options);
+    let mut network = state.network();
+    network.transfer_delta_to_organization_balance();
+    network.dump_network_info();
+    if let Some(fee_sink) = network.fee_sink() {
+        println!("Fees of {} go to {fee_sink}", state.transaction_fee());
+    }
+    let blockchain = network.blockchain();
+    println!(
+        "Blockchain: {} blocks of up to {} transactions, epoch {}, latest block {}",
+        blockchain.height(),
+        state.block_size(),
+        blockchain.epoch(),
+        encode_hex(&blockchain.latest_block_hash())
+    );
+    if options.has("blocks") {
+        for block in blockchain.blocks() {
+            println!(
+                "  Block {}: {} transactions, {}",
+                block.height(),
+                block.len(),
+                encode_hex(block.hash())
+            );
+        }
+    }
+}
+
+pub fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, arguments)) = arguments.split_first() else {
+        fail("No command given");
+    };
+    let Some(known) = known_options(command) else {
+        fail(&format!("Unknown command {command}"));
+    };
+    let options = Options::parse(arguments, known);
+    match command.as_str() {
+        "init" => init(&options),
+        "simulate" => simulate(&options),
+        "prove" => prove(&options),
+        "end-epoch" => end_epoch(&options),
+        "verify" => verify(&options),
+        "inspect" => inspect(&options),
+        "help" | "--help" => println!("{USAGE}"),
+        _ => fail(&format!("Unknown command {command}")),
+    }
+}
@@ -1,19 +1,14 @@
 use ark_bn254::Fr;
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
-use ark_relations::r1cs::ConstraintSystem;
-use ark_std::{test_rng, UniformRand};
-use ducat::{
-    core::{network::Network, org::Organization, run_config::RUN_CONFIG, transaction::Transaction},
-    utils::generate_random_in_range,
+use ducat::core::{
+    key_derivation::MasterSeed, network::Network, org::Organization, run_config::RUN_CONFIG,
+    transaction_builder::TransactionBuilder,
 };
-use rand::{seq::IteratorRandom, Rng};
+use rand::{rngs::OsRng, Rng};
 use std::time::Instant;
 
 pub fn main() {
     std::env::set_var("RUST_BACKTRACE", "full");
-
-    let mut rng = test_rng();
-    let cs = ConstraintSystem::<Fr>::new_ref();
+    RUN_CONFIG.configure_thread_pool();
 
     // Create and add organizations to the network
     let mut network = Network::<Fr>::new();
@@ -24,15 +19,13 @@ pub fn main() {
     // TODO: At the beginning of each new epoch should we change something about the organization's initial balances or their addresses?
     //       This may not necessarily be needed of we consider each run their oen epoch
     for i in 0..RUN_CONFIG.org_count {
-        let unused_serial_numbers = Organization::create_unused_serial_numbers_list(&cs);
-
         let org_name = format!("org{}", i + 1);
         let initial_balance = rand::thread_rng().gen_range(5..500000); // Random initial balance
         let addresses = Organization::create_known_addresses(
-            &cs,
+            &MasterSeed::random(&mut OsRng),
             RUN_CONFIG.addresses_per_organization,
-            i * RUN_CONFIG.addresses_per_organization,
         );
+        let unused_serial_numbers = Organization::create_unused_serial_numbers_list(&addresses);
         let organization = Organization::new(
             org_name.clone(),
             initial_balance,
@@ -42,11 +35,17 @@ pub fn main() {
         organizations.push(organization.clone()); // Store organization for later access
         network.add_organization(organization);
     }
+    if RUN_CONFIG.transaction_fee > 0 {
+        // The operator collects the fees, it holds no addresses of its own
+        network.add_organization(Organization::new("operator".to_owned(), 0, vec![], vec![]));
+        network.set_fee_sink("operator");
+    }
+    if RUN_CONFIG.block_size > 0 {
+        network.set_block_size(RUN_CONFIG.block_size);
+    }
 
     // Generate random transaction data
     for _ in 0..RUN_CONFIG.transaction_count {
-        let tid = FpVar::new_input(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
-
         // Select random sender and receiver organizations
         let sender_index = rand::thread_rng().gen_range(0..RUN_CONFIG.org_count);
         let receiver_index = rand::thread_rng().gen_range(0..RUN_CONFIG.org_count);
@@ -58,41 +57,55 @@ pub fn main() {
             receiver_index
         };
 
-        // Select random addresses from the organizations
-        let sender_address = organizations[sender_index]
-            .known_addresses() // Returns a `&HashSet<String>`
-            .iter() // Create an iterator over the addresses
-            .choose(&mut rng)
-            .cloned()
-            .unwrap();
-        let receiver_address = organizations[receiver_index]
-            .known_addresses() // Returns a `&HashSet<String>`
-            .iter() // Create an iterator over the addresses
-            .choose(&mut rng)
-            .cloned()
-            .unwrap();
-        let sn_secret = FpVar::new_input(cs.clone(), || Ok(generate_random_in_range())).unwrap();
-
-        // Create the transaction
-        let value = rand::thread_rng().gen_range(0..100);
-        let transaction = Transaction::new(tid, value, sender_address, receiver_address, sn_secret);
-
-        // println!(
-        //     "Forwarding transaction of value: {} from sender: {:?} to receiver: {:?}",
-        //     transaction.value(),
-        //     transaction.sender_address(),
-        //     transaction.receiver_address()
-        // );
+        // Create the transaction, the builder picks the addresses, the serial number and the transaction id
+        let value = rand::thread_rng().gen_range(1..100);
+        let transaction = match TransactionBuilder::new(&network)
+            .from_organization(&organizations[sender_index].identifier())
+            .to_organization(&organizations[receiver_index].identifier())
+            .value(value)
+            .fee(RUN_CONFIG.transaction_fee)
+            .build()
+        {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                println!("Skipping transaction: {error}");
+                continue;
+            }
+        };
 
-        // Forward the transaction to the network
-        network.forward_transaction(transaction);
+        // Hand the receiver its opening, then forward the shared transaction to the network or queue it until there are
+        // enough pending transactions for a block
+        network.send_openings(&transaction);
+        if RUN_CONFIG.block_size == 0 {
+            network.forward_transaction(transaction.shared().clone());
+        } else {
+            network
+                .submit_transaction(transaction.shared().clone())
+                .expect("Built transactions are valid");
+            if network.mempool().len() >= RUN_CONFIG.block_size {
+                produce_block(&mut network);
+            }
+        }
+    }
+    while !network.mempool().is_empty() {
+        produce_block(&mut network);
     }
+    println!("Produced {} blocks", network.blockchain().height());
     let start = Instant::now();
     // network.dump_network_info();
     println!("Transferring deltas to org balance");
     network.transfer_delta_to_organization_balance();
     // network.dump_network_info();
     network.validate_all_epoch_deltas_and_final_balances();
+    network.publish_delta_commitments();
+    println!("Deltas sum to zero: {}", network.verify_zero_sum());
     network.clean_deltas_and_balances_at_epoch_end();
     println!("Nova::prove_step: {:?}", start.elapsed());
 }
+
+fn produce_block(network: &mut Network<Fr>) {
+    let (_, dropped) = network.produce_block();
+    for (_, error) in dropped {
+        println!("Dropping pending transaction: {error}");
+    }
+}
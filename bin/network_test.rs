@@ -1,7 +1,7 @@
 use ark_bn254::Fr;
-use ark_relations::r1cs::ConstraintSystem;
-use ducat::core::{org::Organization, run_config::RUN_CONFIG};
+use ducat::core::{key_derivation::MasterSeed, org::Organization, run_config::RUN_CONFIG};
 use indicatif::ProgressIterator;
+use rand::rngs::OsRng;
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
@@ -10,14 +10,13 @@ pub fn main() {
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
     std::env::set_var("RUST_BACKTRACE", "full");
+    RUN_CONFIG.configure_thread_pool();
 
-    let cs = ConstraintSystem::<Fr>::new_ref();
     // Create organizations
-    for i in (0..RUN_CONFIG.org_count).progress() {
-        Organization::create_known_addresses(
-            &cs,
+    for _ in (0..RUN_CONFIG.org_count).progress() {
+        Organization::<Fr>::create_known_addresses(
+            &MasterSeed::random(&mut OsRng),
             RUN_CONFIG.addresses_per_organization,
-            i * RUN_CONFIG.addresses_per_organization,
         );
     }
 }
@@ -1,43 +1,100 @@
-use ark_crypto_primitives::crh::{
-    sha256::constraints::{Sha256Gadget, UnitVar},
-    CRHSchemeGadget,
-};
+use ark_ed_on_bn254::EdwardsAffine;
 use ark_ff::PrimeField;
-use ark_r1cs_std::{fields::fp::FpVar, ToBytesGadget, ToConstraintFieldGadget};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
 
+use super::{signature::verification_key, wire::hex};
+use crate::utils::sha256_field_element;
+
+/// An address with its secret key. It never leaves its owner, transactions and the wire only carry the
+/// [`PublicAddress`]
 #[derive(Clone, Debug)]
 pub struct Address<F: PrimeField> {
-    public_key: FpVar<F>,
-    secret_key: FpVar<F>,
+    public_key: F,
+    secret_key: F,
+    verification_key: EdwardsAffine, // used to check transaction signatures made with the secret key
 }
 /// According to the Zcash paper, this is the address generation procedure. Hash the secret key to generate the pairs (pk, sk)
 impl<F> Address<F>
 where
     F: PrimeField,
 {
-    pub fn new(secret_key: &FpVar<F>) -> Self {
-        // Get the byte representation of the secret key as Vec<UInt8<F>>
-        let secret_key_bytes = secret_key.to_bytes().unwrap();
-
-        // Create a UnitVar instance
-        let unit_var: UnitVar<F> = UnitVar::default();
-
-        // Use the secret_key_bytes directly in the hash computation
-        let sn = &Sha256Gadget::evaluate(&unit_var, &secret_key_bytes)
-            .unwrap()
-            .0
-            .to_constraint_field()
-            .unwrap()[0];
-
+    pub fn new(secret_key: F) -> Self {
         Self {
-            public_key: sn.clone(),
-            secret_key: secret_key.clone(),
+            public_key: sha256_field_element(&secret_key),
+            verification_key: verification_key(&secret_key),
+            secret_key,
         }
     }
-    pub fn public_key(&self) -> &FpVar<F> {
+    pub fn public_key(&self) -> &F {
         &self.public_key
     }
-    pub fn secret_key(&self) -> &FpVar<F> {
+    pub fn secret_key(&self) -> &F {
         &self.secret_key
     }
+    pub fn verification_key(&self) -> &EdwardsAffine {
+        &self.verification_key
+    }
+    /// What others get to see of the address: enough to pay it and to check its signatures
+    pub fn public_address(&self) -> PublicAddress<F> {
+        PublicAddress {
+            public_key: self.public_key,
+            verification_key: self.verification_key,
+        }
+    }
+}
+
+/// The public half of an [`Address`], part of the wire encoding (see [`crate::core::wire`])
+#[derive(
+    Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+#[serde(bound = "")]
+pub struct PublicAddress<F: PrimeField> {
+    #[serde(with = "hex")]
+    public_key: F,
+    #[serde(with = "hex")]
+    verification_key: EdwardsAffine,
+}
+impl<F> PublicAddress<F>
+where
+    F: PrimeField,
+{
+    pub fn public_key(&self) -> &F {
+        &self.public_key
+    }
+    pub fn verification_key(&self) -> &EdwardsAffine {
+        &self.verification_key
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_crypto_primitives::crh::{
+        sha256::constraints::{Sha256Gadget, UnitVar},
+        CRHSchemeGadget,
+    };
+    use ark_r1cs_std::{
+        alloc::AllocVar, fields::fp::FpVar, R1CSVar, ToBytesGadget, ToConstraintFieldGadget,
+    };
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_native_public_key_matches_gadget() {
+        let secret_key = Fr::from(42u64);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let secret_key_var = FpVar::new_witness(cs, || Ok(secret_key)).unwrap();
+        let public_key_var =
+            Sha256Gadget::evaluate(&UnitVar::default(), &secret_key_var.to_bytes().unwrap())
+                .unwrap()
+                .0
+                .to_constraint_field()
+                .unwrap()[0]
+                .clone();
+
+        assert_eq!(
+            *Address::new(secret_key).public_key(),
+            public_key_var.value().unwrap()
+        );
+    }
 }
@@ -0,0 +1,46 @@
+use std::fmt::Display;
+
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+
+/// Identifies one of the assets (BTC, ETH, ...) an organization can hold. Asset `0` is the network's native asset,
+/// which is what transactions and notes carry unless told otherwise.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct AssetId(u32);
+impl AssetId {
+    pub const NATIVE: Self = Self(0);
+
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+    pub fn to_le_bytes(self) -> [u8; 4] {
+        self.0.to_le_bytes()
+    }
+    pub fn to_field<F: PrimeField>(self) -> F {
+        F::from(self.0)
+    }
+}
+impl Display for AssetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "asset {}", self.0)
+    }
+}
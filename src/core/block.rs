@@ -0,0 +1,108 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::wire::hex;
+use crate::gadgets::merkle_gadget::MerkleTreeGadget;
+
+/// Domain separator for block hashes
+const BLOCK_HASH_DOMAIN: &[u8] = b"ducat/block/v1";
+
+/// A batch of transactions appended to the blockchain together, linked to the block before it by its hash
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Block<F: PrimeField> {
+    height: u64,    // number of blocks before this one
+    timestamp: u64, // simulated time the block was produced at
+    #[serde(with = "hex")]
+    previous_hash: [u8; 32], // hash of the previous block, all zeros for the first block
+    #[serde(with = "hex")]
+    transaction_roots: Vec<F>, // roots of the block's transactions in the order they were applied
+    #[serde(with = "hex")]
+    merkle_root: F, // Merkle root over the transaction roots
+    #[serde(with = "hex")]
+    hash: [u8; 32],
+}
+impl<F> Block<F>
+where
+    F: PrimeField,
+{
+    pub fn new(
+        height: u64,
+        timestamp: u64,
+        previous_hash: [u8; 32],
+        transaction_roots: Vec<F>,
+    ) -> Self {
+        let mut block = Self {
+            height,
+            timestamp,
+            previous_hash,
+            merkle_root: Self::merkle_root_of(&transaction_roots),
+            transaction_roots,
+            hash: [0; 32],
+        };
+        block.hash = block.compute_hash();
+        block
+    }
+    /// Merkle root over transaction roots, zero for an empty block
+    pub fn merkle_root_of(transaction_roots: &[F]) -> F {
+        if transaction_roots.is_empty() {
+            return F::zero();
+        }
+        MerkleTreeGadget::create_root_hash(transaction_roots.to_vec())
+    }
+    /// `hash = SHA256(domain || height || timestamp || previous_hash || merkle_root)`
+    pub fn compute_hash(&self) -> [u8; 32] {
+        let mut input = BLOCK_HASH_DOMAIN.to_vec();
+        input.extend(self.height.to_le_bytes());
+        input.extend(self.timestamp.to_le_bytes());
+        input.extend(self.previous_hash);
+        input.extend(self.merkle_root.into_bigint().to_bytes_le());
+        Sha256::digest(input).into()
+    }
+    /// Whether the Merkle root and the hash match the block's contents
+    pub fn is_consistent(&self) -> bool {
+        self.merkle_root == Self::merkle_root_of(&self.transaction_roots)
+            && self.hash == self.compute_hash()
+    }
+    /// Whether `blocks` form a chain from the first block on: every block matches its contents, has its height and
+    /// links to the block before it
+    pub fn verify_chain(blocks: &[Self]) -> bool {
+        let mut previous_hash = [0; 32];
+        for (height, block) in blocks.iter().enumerate() {
+            if block.height != height as u64
+                || block.previous_hash != previous_hash
+                || !block.is_consistent()
+            {
+                return false;
+            }
+            previous_hash = block.hash;
+        }
+        true
+    }
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+    pub fn previous_hash(&self) -> &[u8; 32] {
+        &self.previous_hash
+    }
+    pub fn transaction_roots(&self) -> &[F] {
+        &self.transaction_roots
+    }
+    pub fn merkle_root(&self) -> F {
+        self.merkle_root
+    }
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+    pub fn len(&self) -> usize {
+        self.transaction_roots.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.transaction_roots.is_empty()
+    }
+}
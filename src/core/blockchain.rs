@@ -1,7 +1,16 @@
 use std::{collections::HashMap, fmt::Display};
 
 use ark_ff::PrimeField;
-use ark_r1cs_std::{fields::fp::FpVar, R1CSVar};
+
+use super::{
+    asset::AssetId,
+    block::Block,
+    disjointness::AddressSetCommitment,
+    external::ExternalFlow,
+    membership::{members, MembershipRecord},
+    pedersen::ValueCommitment,
+};
+use crate::gadgets::merkle_gadget::MerkleTreeGadget;
 #[allow(clippy::upper_case_acronyms)]
 type ROOT<F> = F;
 type SN<F> = F;
@@ -9,6 +18,15 @@ type SN<F> = F;
 #[derive(Clone)]
 pub struct Blockchain<F: PrimeField> {
     inner: HashMap<SN<F>, ROOT<F>>,
+    note_commitments: Vec<F>, // append-only list of every note commitment ever created, in order
+    value_commitments: HashMap<ROOT<F>, ValueCommitment>, // the hidden amount of each transaction
+    assets: HashMap<SN<F>, AssetId>, // the asset each transaction moved
+    delta_commitments: HashMap<usize, HashMap<(String, AssetId), ValueCommitment>>, // delta commitments published in each epoch
+    external_flows: HashMap<usize, HashMap<AssetId, ExternalFlow>>, // flows in and out of the organizations published in each epoch
+    address_sets: HashMap<usize, HashMap<String, AddressSetCommitment>>, // address set commitments published in each epoch
+    blocks: Vec<Block<F>>, // every block in order, each linked to the one before it
+    epoch_ends: Vec<usize>, // number of blocks at the end of each finished epoch
+    membership: Vec<MembershipRecord>, // every organization joining or leaving, in order
 }
 
 impl<F> Blockchain<F>
@@ -18,23 +36,188 @@ where
     pub fn new() -> Self {
         Self {
             inner: HashMap::new(),
+            note_commitments: Vec::new(),
+            value_commitments: HashMap::new(),
+            assets: HashMap::new(),
+            delta_commitments: HashMap::new(),
+            external_flows: HashMap::new(),
+            address_sets: HashMap::new(),
+            blocks: Vec::new(),
+            epoch_ends: Vec::new(),
+            membership: Vec::new(),
         }
     }
 
-    pub fn append_transaction(&mut self, root: FpVar<F>, serial_number: FpVar<F>) {
-        // Convert FpVar<F> to concrete values
-        let root_value = root.value().unwrap();
-        let sn_value = serial_number.value().unwrap();
-        // Check if the serial number is already in the HashMap
-        if self.inner.contains_key(&sn_value) {
-            // TODO: This is to purely paper over some issue with repeat sn's being added I think its because our random range is not a full u32 spectrum anymore
-            //        but it is unclear
-            return;
-            panic!("The serial number is already in the blockchain!");
-        }
+    /// Record a transaction under each of its serial numbers. Its value commitment is stored once, however many
+    /// serial numbers it spends
+    pub fn append_transaction(
+        &mut self,
+        root: F,
+        serial_numbers: &[F],
+        asset: AssetId,
+        value_commitment: ValueCommitment,
+    ) {
+        for serial_number in serial_numbers {
+            // The network rejects transactions spending a serial number twice before they get here
+            if self.inner.contains_key(serial_number) {
+                panic!("The serial number is already in the blockchain!");
+            }
 
-        // Insert the transaction into the HashMap
-        self.inner.insert(sn_value, root_value);
+            // Insert the transaction into the HashMap
+            self.inner.insert(*serial_number, root);
+            self.assets.insert(*serial_number, asset);
+        }
+        self.value_commitments.insert(root, value_commitment);
+    }
+    /// Append a block on top of the latest one. The block's transactions have to be appended separately
+    pub fn append_block(&mut self, block: Block<F>) {
+        if block.height() != self.height() || block.previous_hash() != &self.latest_block_hash() {
+            panic!("Block does not extend the latest block");
+        }
+        self.blocks.push(block);
+    }
+    pub fn blocks(&self) -> &[Block<F>] {
+        &self.blocks
+    }
+    /// Number of blocks on the chain, which is also the height of the next block
+    pub fn height(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+    /// Hash of the latest block, all zeros before the first block
+    pub fn latest_block_hash(&self) -> [u8; 32] {
+        self.blocks.last().map_or([0; 32], |block| *block.hash())
+    }
+    /// Check that every block matches its contents and links to the block before it
+    pub fn verify_blocks(&self) -> bool {
+        Block::verify_chain(&self.blocks)
+    }
+    /// Close the current epoch, the blocks produced so far belong to it
+    pub fn end_epoch(&mut self) {
+        self.epoch_ends.push(self.blocks.len());
+    }
+    /// Number of the current epoch, counting from zero
+    pub fn epoch(&self) -> usize {
+        self.epoch_ends.len()
+    }
+    /// The blocks of a finished or the current epoch
+    pub fn epoch_blocks(&self, epoch: usize) -> &[Block<F>] {
+        let start = epoch
+            .checked_sub(1)
+            .map_or(0, |previous| self.epoch_ends[previous]);
+        let end = self
+            .epoch_ends
+            .get(epoch)
+            .copied()
+            .unwrap_or(self.blocks.len());
+        &self.blocks[start..end]
+    }
+    pub fn record_membership_change(&mut self, record: MembershipRecord) {
+        self.membership.push(record);
+    }
+    pub fn membership_history(&self) -> &[MembershipRecord] {
+        &self.membership
+    }
+    /// Identifiers of the organizations taking part in an epoch, in the order they joined
+    pub fn members(&self, epoch: usize) -> Vec<String> {
+        members(&self.membership, epoch)
+    }
+    pub fn asset(&self, serial_number: &F) -> Option<AssetId> {
+        self.assets.get(serial_number).copied()
+    }
+    /// The commitment to the amount moved by the transaction with this root
+    pub fn value_commitment(&self, root: &F) -> Option<ValueCommitment> {
+        self.value_commitments.get(root).copied()
+    }
+    /// Publish an organization's delta commitment for `asset` in the current epoch
+    pub fn publish_delta_commitment(
+        &mut self,
+        identifier: String,
+        asset: AssetId,
+        commitment: ValueCommitment,
+    ) {
+        self.delta_commitments
+            .entry(self.epoch())
+            .or_default()
+            .insert((identifier, asset), commitment);
+    }
+    pub fn delta_commitment(
+        &self,
+        epoch: usize,
+        identifier: &str,
+        asset: AssetId,
+    ) -> Option<ValueCommitment> {
+        self.delta_commitments
+            .get(&epoch)?
+            .get(&(identifier.to_owned(), asset))
+            .copied()
+    }
+    /// The delta commitments published in an epoch
+    pub fn delta_commitments(&self, epoch: usize) -> HashMap<(String, AssetId), ValueCommitment> {
+        self.delta_commitments
+            .get(&epoch)
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// Publish the flow of `asset` in and out of the organizations in the current epoch
+    pub fn publish_external_flow(&mut self, asset: AssetId, flow: ExternalFlow) {
+        self.external_flows
+            .entry(self.epoch())
+            .or_default()
+            .insert(asset, flow);
+    }
+    /// The external flows published in an epoch
+    pub fn external_flows(&self, epoch: usize) -> HashMap<AssetId, ExternalFlow> {
+        self.external_flows.get(&epoch).cloned().unwrap_or_default()
+    }
+    /// Publish an organization's address set commitment in the current epoch
+    pub fn publish_address_set_commitment(
+        &mut self,
+        identifier: String,
+        commitment: AddressSetCommitment,
+    ) {
+        self.address_sets
+            .entry(self.epoch())
+            .or_default()
+            .insert(identifier, commitment);
+    }
+    pub fn address_set_commitment(
+        &self,
+        epoch: usize,
+        identifier: &str,
+    ) -> Option<&AddressSetCommitment> {
+        self.address_sets.get(&epoch)?.get(identifier)
+    }
+    /// The address set commitments published in an epoch, ordered by identifier
+    pub fn address_set_commitments(&self, epoch: usize) -> Vec<(String, AddressSetCommitment)> {
+        let mut commitments: Vec<_> = self
+            .address_sets
+            .get(&epoch)
+            .into_iter()
+            .flatten()
+            .map(|(identifier, commitment)| (identifier.clone(), commitment.clone()))
+            .collect();
+        commitments.sort_unstable_by(|(first, _), (second, _)| first.cmp(second));
+        commitments
+    }
+    pub fn append_note_commitment(&mut self, commitment: F) {
+        self.note_commitments.push(commitment);
+    }
+    pub fn has_note_commitment(&self, commitment: &F) -> bool {
+        self.note_commitments.contains(commitment)
+    }
+    /// Whether a note with this serial number has already been spent
+    pub fn is_spent(&self, serial_number: &F) -> bool {
+        self.inner.contains_key(serial_number)
+    }
+    pub fn note_commitments(&self) -> Vec<F> {
+        self.note_commitments.clone()
+    }
+    /// Root of the Merkle tree over all note commitments, zero while there are none
+    pub fn note_commitment_root(&self) -> F {
+        if self.note_commitments.is_empty() {
+            return F::zero();
+        }
+        MerkleTreeGadget::create_root_hash(self.note_commitments.clone())
     }
     pub fn dump_transactions(&self) {
         println!("Blockchain Transactions:");
@@ -51,6 +234,14 @@ where
     pub fn inner(&self) -> HashMap<SN<F>, ROOT<F>> {
         self.inner.clone()
     }
+    /// The transactions that moved `asset`
+    pub fn inner_of_asset(&self, asset: AssetId) -> HashMap<SN<F>, ROOT<F>> {
+        self.inner
+            .iter()
+            .filter(|(serial_number, _)| self.assets.get(serial_number) == Some(&asset))
+            .map(|(serial_number, root)| (*serial_number, *root))
+            .collect()
+    }
 }
 impl<F> Default for Blockchain<F>
 where
@@ -70,3 +261,66 @@ impl<F: PrimeField> Display for Blockchain<F> {
         Ok(())
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        network::fixtures::{submit, two_organization_network},
+        transaction::Transaction,
+        wire::WireFormat,
+    };
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_block_production() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        network.set_block_size(2);
+        network.set_block_interval(5);
+        let transactions: Vec<_> = (0..3u64)
+            .map(|rho| {
+                Transaction::new(
+                    Fr::from(rho),
+                    10,
+                    first_addresses[0].clone(),
+                    second_addresses[0].public_address(),
+                    Fr::from(rho),
+                    &range_key,
+                )
+            })
+            .collect();
+        for t in &transactions {
+            submit(&mut network, t.clone()).unwrap();
+        }
+
+        // Nothing lands on the chain until the block interval has passed, then a block takes at most two transactions
+        assert!(network.advance_time(4).is_empty());
+        assert_eq!(network.organizations()["org2"].delta(AssetId::NATIVE), 0);
+        let blocks = network.advance_time(1);
+        assert_eq!(blocks.len(), 1);
+        let (first, dropped) = &blocks[0];
+        assert!(dropped.is_empty());
+        assert_eq!((first.height(), first.timestamp(), first.len()), (0, 5, 2));
+        assert_eq!(
+            first.merkle_root(),
+            Block::merkle_root_of(&[transactions[0].root(), transactions[1].root()])
+        );
+        assert_eq!(network.organizations()["org2"].delta(AssetId::NATIVE), 20);
+        assert_eq!(network.mempool().len(), 1);
+
+        // The rest goes into the next block, linked to the first, and epochs are ranges of blocks
+        network.clean_deltas_and_balances_at_epoch_end();
+        let (second, _) = network.produce_block();
+        assert_eq!(second.previous_hash(), first.hash());
+        assert_eq!(second.transaction_roots(), &[transactions[2].root()]);
+        assert_eq!(network.blockchain().epoch_blocks(0).len(), 1);
+        assert_eq!(
+            network.blockchain().epoch_blocks(1)[0].hash(),
+            second.hash()
+        );
+        assert!(network.blockchain().verify_blocks());
+        let decoded = Block::<Fr>::from_bytes(&second.to_bytes()).unwrap();
+        assert!(decoded.is_consistent());
+        assert_eq!(decoded.hash(), second.hash());
+    }
+}
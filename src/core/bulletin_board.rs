@@ -0,0 +1,589 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use ark_ed_on_bn254::{EdwardsAffine, Fq};
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{
+    address::Address,
+    asset::AssetId,
+    disjointness::AddressSetCommitment,
+    external::ExternalFlow,
+    membership::{signing_key, MembershipRecord},
+    pedersen::ValueCommitment,
+    signature::{self, Signature},
+    wire::hex,
+};
+use crate::{gadgets::merkle_gadget::MerkleTreeGadget, verifier::ProofSubmission};
+
+/// Domain separator for posting hashes
+const POSTING_DOMAIN: &[u8] = b"ducat/bulletin-board/v1";
+/// Domain separator for the hash of an epoch's external flows
+const EXTERNAL_FLOWS_DOMAIN: &[u8] = b"ducat/external-flows/v1";
+
+/// Why a posting was turned down by the bulletin board
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BulletinBoardError {
+    /// The posting doesn't link to the latest posting on the board
+    DoesNotExtendTip,
+    /// The posting's hash doesn't match its contents or the signature doesn't verify
+    InvalidSignature,
+    /// The posting isn't signed with the key the organization registered when it joined the network, or the
+    /// organization wasn't a member in the posting's epoch
+    UnknownSigner(String),
+    /// The organization already posted for this epoch
+    AlreadyPosted(String, usize),
+    /// The organization has no address to sign its posting with
+    NoSigningAddress(String),
+    /// The posting carries proofs of another organization
+    ForeignProof(String),
+    /// The external flows of this epoch are already on the board
+    FlowsAlreadyPosted(usize),
+    /// The external flows of this epoch have to be on the board before anything is posted for it
+    FlowsNotPosted(usize),
+    /// The posting signs other external flows than the ones on the board for its epoch
+    FlowsMismatch(String),
+}
+impl Display for BulletinBoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DoesNotExtendTip => {
+                write!(f, "posting doesn't extend the latest posting on the board")
+            }
+            Self::InvalidSignature => write!(f, "posting hash or signature is invalid"),
+            Self::UnknownSigner(identifier) => {
+                write!(f, "posting is not signed with the key of {identifier}")
+            }
+            Self::AlreadyPosted(identifier, epoch) => {
+                write!(
+                    f,
+                    "organization {identifier} already posted for epoch {epoch}"
+                )
+            }
+            Self::NoSigningAddress(identifier) => {
+                write!(f, "organization {identifier} has no address to sign with")
+            }
+            Self::ForeignProof(identifier) => {
+                write!(f, "posting carries a proof of organization {identifier}")
+            }
+            Self::FlowsAlreadyPosted(epoch) => {
+                write!(f, "external flows of epoch {epoch} are already posted")
+            }
+            Self::FlowsNotPosted(epoch) => {
+                write!(f, "external flows of epoch {epoch} are not posted yet")
+            }
+            Self::FlowsMismatch(identifier) => {
+                write!(
+                    f,
+                    "posting of {identifier} signs other external flows than the posted ones"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for BulletinBoardError {}
+
+/// Merkle root over serial numbers, zero for an empty set
+pub fn serial_number_root<F: PrimeField>(serial_numbers: &[F]) -> F {
+    if serial_numbers.is_empty() {
+        return F::zero();
+    }
+    MerkleTreeGadget::create_root_hash(serial_numbers.to_vec())
+}
+
+/// `SHA256(domain || (asset || deposits || withdrawals || commitment || blinding)*)` over the flows in ascending asset
+/// order
+pub fn external_flows_hash(flows: &HashMap<AssetId, ExternalFlow>) -> [u8; 32] {
+    let mut input = EXTERNAL_FLOWS_DOMAIN.to_vec();
+    for (asset, flow) in flows.iter().collect::<BTreeMap<_, _>>() {
+        input.extend(asset.to_le_bytes());
+        input.extend(flow.deposits().to_le_bytes());
+        input.extend(flow.withdrawals().to_le_bytes());
+        flow.commitment().serialize_compressed(&mut input).unwrap();
+        flow.blinding().serialize_compressed(&mut input).unwrap();
+    }
+    Sha256::digest(input).into()
+}
+
+/// What an organization publishes about one epoch, signed by one of its addresses and linked to the posting before it.
+/// It also signs the hash of the epoch's external flows, so the flows are as tamper evident as the postings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Posting<F: PrimeField> {
+    identifier: String,
+    epoch: usize,
+    delta_commitments: BTreeMap<AssetId, ValueCommitment>,
+    address_set: AddressSetCommitment,
+    #[serde(with = "hex")]
+    serial_number_root: F, // Merkle root over the serial numbers the organization spent or received
+    #[serde(with = "hex")]
+    proofs: Vec<ProofSubmission>,
+    #[serde(with = "hex")]
+    external_flows_hash: [u8; 32], // hash of the external flows on the board for the epoch
+    #[serde(with = "hex")]
+    verification_key: EdwardsAffine, // key the posting is signed with
+    signature: Signature, // signature over the digest
+    #[serde(with = "hex")]
+    previous_hash: [u8; 32], // hash of the previous posting, all zeros for the first posting
+    #[serde(with = "hex")]
+    hash: [u8; 32],
+}
+impl<F> Posting<F>
+where
+    F: PrimeField,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        identifier: String,
+        epoch: usize,
+        delta_commitments: BTreeMap<AssetId, ValueCommitment>,
+        address_set: AddressSetCommitment,
+        serial_number_root: F,
+        proofs: Vec<ProofSubmission>,
+        external_flows_hash: [u8; 32],
+        previous_hash: [u8; 32],
+        signer: &Address<F>,
+    ) -> Result<Self, BulletinBoardError> {
+        if let Some(submission) = proofs
+            .iter()
+            .find(|submission| submission.identifier() != identifier)
+        {
+            return Err(BulletinBoardError::ForeignProof(
+                submission.identifier().to_owned(),
+            ));
+        }
+        let mut posting = Self {
+            identifier,
+            epoch,
+            delta_commitments,
+            address_set,
+            serial_number_root,
+            proofs,
+            external_flows_hash,
+            verification_key: *signer.verification_key(),
+            signature: Signature::default(),
+            previous_hash,
+            hash: [0; 32],
+        };
+        posting.signature = signature::sign(signer.secret_key(), posting.message());
+        posting.hash = posting.compute_hash();
+        Ok(posting)
+    }
+    /// `SHA256(domain || previous_hash || identifier || epoch || commitments || address set || SN root || proofs ||
+    /// external flows hash || verification key)`
+    pub fn digest(&self) -> [u8; 32] {
+        let mut input = POSTING_DOMAIN.to_vec();
+        input.extend(self.previous_hash);
+        input.extend((self.identifier.len() as u64).to_le_bytes());
+        input.extend(self.identifier.as_bytes());
+        input.extend((self.epoch as u64).to_le_bytes());
+        for (asset, commitment) in &self.delta_commitments {
+            input.extend(asset.to_le_bytes());
+            commitment.serialize_compressed(&mut input).unwrap();
+        }
+        input.extend(self.address_set.salt());
+        input.extend(self.address_set.root());
+        for tag in self.address_set.tags() {
+            input.extend(tag);
+        }
+        input.extend(self.serial_number_root.into_bigint().to_bytes_le());
+        for submission in &self.proofs {
+            input.extend(submission.asset().to_le_bytes());
+            submission
+                .epoch_proof()
+                .serialize_compressed(&mut input)
+                .unwrap();
+            submission
+                .asset_proof()
+                .serialize_compressed(&mut input)
+                .unwrap();
+        }
+        input.extend(self.external_flows_hash);
+        self.verification_key
+            .serialize_compressed(&mut input)
+            .unwrap();
+        Sha256::digest(input).into()
+    }
+    /// The digest as a message for the signature
+    fn message(&self) -> Fq {
+        Fq::from_le_bytes_mod_order(&self.digest())
+    }
+    /// `hash = SHA256(digest || signature)`
+    pub fn compute_hash(&self) -> [u8; 32] {
+        let mut input = self.digest().to_vec();
+        self.signature.serialize_compressed(&mut input).unwrap();
+        Sha256::digest(input).into()
+    }
+    /// Whether the signature verifies and the hash matches the posting's contents
+    pub fn is_authentic(&self) -> bool {
+        signature::verify(&self.verification_key, self.message(), &self.signature)
+            && self.hash == self.compute_hash()
+    }
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+    pub fn delta_commitments(&self) -> &BTreeMap<AssetId, ValueCommitment> {
+        &self.delta_commitments
+    }
+    pub fn address_set(&self) -> &AddressSetCommitment {
+        &self.address_set
+    }
+    pub fn address_set_root(&self) -> &[u8; 32] {
+        self.address_set.root()
+    }
+    pub fn serial_number_root(&self) -> F {
+        self.serial_number_root
+    }
+    pub fn proofs(&self) -> &[ProofSubmission] {
+        &self.proofs
+    }
+    pub fn external_flows_hash(&self) -> &[u8; 32] {
+        &self.external_flows_hash
+    }
+    pub fn verification_key(&self) -> &EdwardsAffine {
+        &self.verification_key
+    }
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+    pub fn previous_hash(&self) -> &[u8; 32] {
+        &self.previous_hash
+    }
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+}
+
+/// Append-only, hash-chained log of what organizations publish every epoch. Auditors and customers read from it
+/// instead of asking organizations directly, so every reader sees the same artifacts.
+///
+/// Postings have to be signed with the key the organization registered when it joined, as recorded in the
+/// blockchain's membership history. Next to the postings the board holds the network's external flows of every epoch,
+/// which go up before the epoch's postings so that every posting signs their hash.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BulletinBoard<F: PrimeField> {
+    postings: Vec<Posting<F>>,
+    external_flows: BTreeMap<usize, HashMap<AssetId, ExternalFlow>>, // flows in and out of the organizations by epoch
+}
+impl<F> Default for BulletinBoard<F>
+where
+    F: PrimeField,
+{
+    fn default() -> Self {
+        Self {
+            postings: Vec::new(),
+            external_flows: BTreeMap::new(),
+        }
+    }
+}
+impl<F> BulletinBoard<F>
+where
+    F: PrimeField,
+{
+    /// Append a posting on top of the latest one, checking its signer against the `membership` records
+    pub fn post(
+        &mut self,
+        posting: Posting<F>,
+        membership: &[MembershipRecord],
+    ) -> Result<(), BulletinBoardError> {
+        if posting.previous_hash != self.tip_hash() {
+            return Err(BulletinBoardError::DoesNotExtendTip);
+        }
+        if !posting.is_authentic() {
+            return Err(BulletinBoardError::InvalidSignature);
+        }
+        if signing_key(membership, &posting.identifier, posting.epoch)
+            != Some(posting.verification_key)
+        {
+            return Err(BulletinBoardError::UnknownSigner(posting.identifier));
+        }
+        if self.posting(&posting.identifier, posting.epoch).is_some() {
+            return Err(BulletinBoardError::AlreadyPosted(
+                posting.identifier,
+                posting.epoch,
+            ));
+        }
+        let flows = self
+            .external_flows
+            .get(&posting.epoch)
+            .ok_or(BulletinBoardError::FlowsNotPosted(posting.epoch))?;
+        if posting.external_flows_hash != external_flows_hash(flows) {
+            return Err(BulletinBoardError::FlowsMismatch(posting.identifier));
+        }
+        self.postings.push(posting);
+        Ok(())
+    }
+    /// Publish the network's external flows of an epoch, once and ahead of the epoch's postings
+    pub fn post_external_flows(
+        &mut self,
+        epoch: usize,
+        flows: HashMap<AssetId, ExternalFlow>,
+    ) -> Result<(), BulletinBoardError> {
+        if self.external_flows.contains_key(&epoch) {
+            return Err(BulletinBoardError::FlowsAlreadyPosted(epoch));
+        }
+        self.external_flows.insert(epoch, flows);
+        Ok(())
+    }
+    /// The external flows posted for an epoch, keyed like the blockchain's
+    pub fn external_flows(&self, epoch: usize) -> HashMap<AssetId, ExternalFlow> {
+        self.external_flows.get(&epoch).cloned().unwrap_or_default()
+    }
+    /// Hash of the latest posting, all zeros for an empty board
+    pub fn tip_hash(&self) -> [u8; 32] {
+        self.postings.last().map_or([0; 32], |posting| posting.hash)
+    }
+    pub fn postings(&self) -> &[Posting<F>] {
+        &self.postings
+    }
+    /// The postings of one epoch, in the order they were made
+    pub fn postings_of(&self, epoch: usize) -> Vec<&Posting<F>> {
+        self.postings
+            .iter()
+            .filter(|posting| posting.epoch == epoch)
+            .collect()
+    }
+    pub fn posting(&self, identifier: &str, epoch: usize) -> Option<&Posting<F>> {
+        self.postings
+            .iter()
+            .find(|posting| posting.identifier == identifier && posting.epoch == epoch)
+    }
+    /// The delta commitments posted for an epoch, keyed like the blockchain's
+    pub fn delta_commitments(&self, epoch: usize) -> HashMap<(String, AssetId), ValueCommitment> {
+        self.postings_of(epoch)
+            .into_iter()
+            .flat_map(|posting| {
+                posting
+                    .delta_commitments
+                    .iter()
+                    .map(|(asset, commitment)| ((posting.identifier.clone(), *asset), *commitment))
+            })
+            .collect()
+    }
+    /// The address set commitments posted for an epoch, ready for [`crate::core::disjointness::find_overlaps`]
+    pub fn address_set_commitments(&self, epoch: usize) -> Vec<(String, AddressSetCommitment)> {
+        self.postings_of(epoch)
+            .into_iter()
+            .map(|posting| (posting.identifier.clone(), posting.address_set.clone()))
+            .collect()
+    }
+    /// The proofs posted for an epoch, ready for [`crate::verifier::Auditor::audit`]
+    pub fn submissions(&self, epoch: usize) -> Vec<ProofSubmission> {
+        self.postings_of(epoch)
+            .into_iter()
+            .flat_map(|posting| posting.proofs.iter().cloned())
+            .collect()
+    }
+    /// Whether every posting is authentic, links to the one before it, is signed with the key its organization
+    /// registered in the `membership` records and signs the external flows on the board for its epoch. Flows no
+    /// posting signs don't count as verified either
+    pub fn verify_chain(&self, membership: &[MembershipRecord]) -> bool {
+        let mut previous_hash = [0; 32];
+        for posting in &self.postings {
+            if posting.previous_hash != previous_hash
+                || !posting.is_authentic()
+                || signing_key(membership, &posting.identifier, posting.epoch)
+                    != Some(posting.verification_key)
+                || self
+                    .external_flows
+                    .get(&posting.epoch)
+                    .map(external_flows_hash)
+                    != Some(posting.external_flows_hash)
+            {
+                return false;
+            }
+            previous_hash = posting.hash;
+        }
+        self.external_flows
+            .keys()
+            .all(|epoch| !self.postings_of(*epoch).is_empty())
+    }
+    pub fn len(&self) -> usize {
+        self.postings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.postings.is_empty()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        key_derivation::MasterSeed, membership::MembershipChange, org::Organization,
+    };
+    use ark_bn254::Fr;
+    use ark_groth16::Proof;
+
+    #[test]
+    fn test_bulletin_board() {
+        let addresses = Organization::<Fr>::create_known_addresses(&MasterSeed::new([1u8; 32]), 2);
+        let public_keys: Vec<Fr> = addresses
+            .iter()
+            .map(|address| *address.public_key())
+            .collect();
+        // org1 joined with its first address as signing key, org2 never joined
+        let membership = [MembershipRecord::new(
+            "org1".to_owned(),
+            MembershipChange::Joined,
+            0,
+            0,
+            BTreeMap::new(),
+            Some(*addresses[0].verification_key()),
+        )];
+        // Only epoch 1 saw value move in or out of the organizations
+        let flows = HashMap::from([(AssetId::NATIVE, ExternalFlow::default())]);
+        let flows_of = |epoch: usize| {
+            if epoch == 1 {
+                flows.clone()
+            } else {
+                HashMap::new()
+            }
+        };
+        let posting_for =
+            |identifier: &str, epoch: usize, previous_hash: [u8; 32], signer: &Address<Fr>| {
+                Posting::new(
+                    identifier.to_owned(),
+                    epoch,
+                    BTreeMap::from([(AssetId::NATIVE, ValueCommitment::zero())]),
+                    AddressSetCommitment::new([epoch as u8; 32], &public_keys),
+                    serial_number_root(&[Fr::from(1u64), Fr::from(2u64)]),
+                    vec![],
+                    external_flows_hash(&flows_of(epoch)),
+                    previous_hash,
+                    signer,
+                )
+                .unwrap()
+            };
+
+        // The external flows of an epoch go up first, and only once
+        let mut board = BulletinBoard::<Fr>::default();
+        assert_eq!(
+            board.post(
+                posting_for("org1", 0, board.tip_hash(), &addresses[0]),
+                &membership
+            ),
+            Err(BulletinBoardError::FlowsNotPosted(0))
+        );
+        board.post_external_flows(0, flows_of(0)).unwrap();
+        board.post_external_flows(1, flows_of(1)).unwrap();
+        assert_eq!(
+            board.post_external_flows(1, flows.clone()),
+            Err(BulletinBoardError::FlowsAlreadyPosted(1))
+        );
+        assert_eq!(board.external_flows(1), flows);
+        assert!(board.external_flows(0).is_empty());
+        board
+            .post(
+                posting_for("org1", 0, board.tip_hash(), &addresses[0]),
+                &membership,
+            )
+            .unwrap();
+        assert_eq!(
+            board.post(
+                posting_for("org1", 0, board.tip_hash(), &addresses[0]),
+                &membership
+            ),
+            Err(BulletinBoardError::AlreadyPosted("org1".to_owned(), 0))
+        );
+        assert_eq!(
+            board.post(posting_for("org1", 1, [0; 32], &addresses[0]), &membership),
+            Err(BulletinBoardError::DoesNotExtendTip)
+        );
+        // Only the key registered on joining signs for an organization, and a non-member can't post at all
+        assert_eq!(
+            board.post(
+                posting_for("org1", 1, board.tip_hash(), &addresses[1]),
+                &membership
+            ),
+            Err(BulletinBoardError::UnknownSigner("org1".to_owned()))
+        );
+        assert_eq!(
+            board.post(
+                posting_for("org2", 1, board.tip_hash(), &addresses[1]),
+                &membership
+            ),
+            Err(BulletinBoardError::UnknownSigner("org2".to_owned()))
+        );
+        // A posting has to sign the flows that are on the board for its epoch
+        let mut other_flows = posting_for("org1", 0, board.tip_hash(), &addresses[0]);
+        other_flows.epoch = 1;
+        other_flows.signature = signature::sign(addresses[0].secret_key(), other_flows.message());
+        other_flows.hash = other_flows.compute_hash();
+        assert_eq!(
+            board.post(other_flows, &membership),
+            Err(BulletinBoardError::FlowsMismatch("org1".to_owned()))
+        );
+        board
+            .post(
+                posting_for("org1", 1, board.tip_hash(), &addresses[0]),
+                &membership,
+            )
+            .unwrap();
+        assert_eq!(board.len(), 2);
+        assert!(board.verify_chain(&membership));
+        assert!(board
+            .posting("org1", 1)
+            .unwrap()
+            .address_set()
+            .contains(&public_keys[1]));
+        assert_eq!(board.delta_commitments(1).len(), 1);
+
+        // An organization can't post another organization's proofs
+        let foreign = ProofSubmission::new(
+            "org2".to_owned(),
+            AssetId::NATIVE,
+            Proof::default(),
+            Proof::default(),
+        );
+        assert_eq!(
+            Posting::new(
+                "org1".to_owned(),
+                2,
+                BTreeMap::new(),
+                AddressSetCommitment::new([2; 32], &public_keys),
+                Fr::from(0u64),
+                vec![foreign],
+                external_flows_hash(&HashMap::new()),
+                board.tip_hash(),
+                &addresses[0],
+            )
+            .unwrap_err(),
+            BulletinBoardError::ForeignProof("org2".to_owned())
+        );
+
+        // Tampering with anything a posting committed to breaks its signature and the chain
+        let mut forged = posting_for("org1", 2, board.tip_hash(), &addresses[0]);
+        forged.epoch = 1;
+        assert_eq!(
+            board.post(forged, &membership),
+            Err(BulletinBoardError::InvalidSignature)
+        );
+        let mut tampered = board.clone();
+        tampered.postings[0].serial_number_root = Fr::from(3u64);
+        assert!(!tampered.verify_chain(&membership));
+
+        // So does tampering with the flows, and flows no posting signed don't verify
+        let mut tampered = board.clone();
+        let mut withdrawal = ExternalFlow::default();
+        withdrawal.withdraw(
+            5,
+            ValueCommitment::new(5, &Default::default()),
+            Default::default(),
+        );
+        tampered
+            .external_flows
+            .insert(1, HashMap::from([(AssetId::NATIVE, withdrawal)]));
+        assert!(!tampered.verify_chain(&membership));
+        board.post_external_flows(2, HashMap::new()).unwrap();
+        assert!(!board.verify_chain(&membership));
+    }
+}
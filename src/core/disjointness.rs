@@ -0,0 +1,234 @@
+use std::{collections::HashMap, fmt::Display};
+
+use ark_ff::{BigInteger, PrimeField};
+use rs_merkle::{algorithms::Sha256 as MerkleSha256, MerkleTree};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::wire::hex;
+
+/// Domain separator for address tags
+const ADDRESS_TAG_DOMAIN: &[u8] = b"ducat/address-tag/v1";
+
+/// Hide an address public key behind a salted hash, `tag = SHA256(domain || salt || pk)`. Two organizations holding the
+/// same address produce the same tag under the same salt, so shared addresses show up without the tags revealing
+/// which addresses they are. A fresh salt every epoch keeps the tags of different epochs unlinkable.
+pub fn address_tag<F: PrimeField>(salt: &[u8; 32], public_key: &F) -> [u8; 32] {
+    let mut input = ADDRESS_TAG_DOMAIN.to_vec();
+    input.extend(salt);
+    input.extend(public_key.into_bigint().to_bytes_le());
+    Sha256::digest(input).into()
+}
+
+/// An organization's commitment to its set of addresses: the sorted tags of its addresses and a Merkle root over them.
+///
+/// Organizations publish these so auditors can check that no address is claimed by two organizations, and customers
+/// can check their address is among the ones an organization counts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressSetCommitment {
+    #[serde(with = "hex")]
+    salt: [u8; 32],
+    #[serde(with = "hex")]
+    tags: Vec<[u8; 32]>, // one tag per address, in ascending order
+    #[serde(with = "hex")]
+    root: [u8; 32], // Merkle root over the tags, all zeros for an empty set
+}
+impl AddressSetCommitment {
+    pub fn new<F: PrimeField>(salt: [u8; 32], public_keys: &[F]) -> Self {
+        let mut tags: Vec<[u8; 32]> = public_keys
+            .iter()
+            .map(|public_key| address_tag(&salt, public_key))
+            .collect();
+        tags.sort_unstable();
+        Self {
+            salt,
+            root: Self::merkle_root(&tags),
+            tags,
+        }
+    }
+    fn merkle_root(tags: &[[u8; 32]]) -> [u8; 32] {
+        MerkleTree::<MerkleSha256>::from_leaves(tags)
+            .root()
+            .unwrap_or([0; 32])
+    }
+    /// Whether every address is listed once, in order, and the root matches the tags
+    pub fn is_well_formed(&self) -> bool {
+        self.tags.windows(2).all(|pair| pair[0] < pair[1])
+            && self.root == Self::merkle_root(&self.tags)
+    }
+    /// Whether the address is in the set
+    pub fn contains<F: PrimeField>(&self, public_key: &F) -> bool {
+        self.tags
+            .binary_search(&address_tag(&self.salt, public_key))
+            .is_ok()
+    }
+    pub fn salt(&self) -> &[u8; 32] {
+        &self.salt
+    }
+    pub fn tags(&self) -> &[[u8; 32]] {
+        &self.tags
+    }
+    pub fn root(&self) -> &[u8; 32] {
+        &self.root
+    }
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// Why address set commitments couldn't be compared
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisjointnessError {
+    /// The organizations' commitments were made under another salt than the first commitment's
+    SaltMismatch(Vec<String>),
+}
+impl Display for DisjointnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SaltMismatch(identifiers) => {
+                write!(
+                    f,
+                    "address sets of {} were committed under another salt",
+                    identifiers.join(", ")
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for DisjointnessError {}
+
+/// Pairs of organizations whose committed address sets share at least one address, in ascending order. Only
+/// commitments made under the same salt can be compared
+pub fn find_overlaps(
+    commitments: &[(String, AddressSetCommitment)],
+) -> Result<Vec<(String, String)>, DisjointnessError> {
+    let mismatched = salt_mismatches(commitments);
+    if !mismatched.is_empty() {
+        return Err(DisjointnessError::SaltMismatch(mismatched));
+    }
+    let mut owners: HashMap<[u8; 32], &String> = HashMap::new();
+    let mut overlaps = Vec::new();
+    for (identifier, commitment) in commitments {
+        for tag in &commitment.tags {
+            match owners.get(tag) {
+                Some(owner) if *owner != identifier => {
+                    let mut pair = [(*owner).clone(), identifier.clone()];
+                    pair.sort_unstable();
+                    let [first, second] = pair;
+                    overlaps.push((first, second));
+                }
+                Some(_) => {}
+                None => {
+                    owners.insert(*tag, identifier);
+                }
+            }
+        }
+    }
+    overlaps.sort_unstable();
+    overlaps.dedup();
+    Ok(overlaps)
+}
+/// Identifiers of the organizations whose commitment was made under another salt than the first commitment's, in the
+/// order they are listed
+fn salt_mismatches(commitments: &[(String, AddressSetCommitment)]) -> Vec<String> {
+    let Some((_, first)) = commitments.first() else {
+        return Vec::new();
+    };
+    commitments
+        .iter()
+        .filter(|(_, commitment)| commitment.salt != first.salt)
+        .map(|(identifier, _)| identifier.clone())
+        .collect()
+}
+/// Identifiers of the organizations whose commitment is malformed, was made under another salt or shares an address
+/// with another organization, in ascending order (an empty vector means the address sets are disjoint). Commitments
+/// made under the right salt are still compared with each other when others aren't
+pub fn failed_disjointness(commitments: &[(String, AddressSetCommitment)]) -> Vec<String> {
+    let mismatched = salt_mismatches(commitments);
+    let comparable: Vec<(String, AddressSetCommitment)> = commitments
+        .iter()
+        .filter(|(identifier, _)| !mismatched.contains(identifier))
+        .cloned()
+        .collect();
+    let overlapping = find_overlaps(&comparable)
+        .expect("Commitments under the first salt are comparable")
+        .into_iter()
+        .flat_map(|(first, second)| [first, second]);
+    let mut failed: Vec<String> = overlapping
+        .chain(mismatched)
+        .chain(
+            commitments
+                .iter()
+                .filter(|(_, commitment)| !commitment.is_well_formed())
+                .map(|(identifier, _)| identifier.clone()),
+        )
+        .collect();
+    failed.sort_unstable();
+    failed.dedup();
+    failed
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{key_derivation::MasterSeed, org::Organization};
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_address_disjointness() {
+        let public_keys: Vec<Vec<Fr>> = [[1u8; 32], [2u8; 32]]
+            .iter()
+            .map(|seed| {
+                Organization::<Fr>::create_known_addresses(&MasterSeed::new(*seed), 3)
+                    .iter()
+                    .map(|address| *address.public_key())
+                    .collect()
+            })
+            .collect();
+        let salt = [7u8; 32];
+        let first = AddressSetCommitment::new(salt, &public_keys[0]);
+        let second = AddressSetCommitment::new(salt, &public_keys[1]);
+        assert!(first.is_well_formed());
+        assert!(first.contains(&public_keys[0][2]));
+        assert!(!first.contains(&public_keys[1][0]));
+        let mut commitments = vec![
+            ("org1".to_owned(), first.clone()),
+            ("org2".to_owned(), second),
+        ];
+        assert_eq!(find_overlaps(&commitments), Ok(vec![]));
+
+        // A third organization claiming one of org1's addresses is caught, one listing an address twice is malformed
+        let colluding = AddressSetCommitment::new(salt, &[public_keys[0][1], Fr::from(5u64)]);
+        commitments.insert(0, ("org3".to_owned(), colluding));
+        assert_eq!(
+            find_overlaps(&commitments),
+            Ok(vec![("org1".to_owned(), "org3".to_owned())])
+        );
+        let repeated = AddressSetCommitment::new(salt, &[Fr::from(5u64), Fr::from(5u64)]);
+        assert!(!repeated.is_well_formed());
+
+        // Another salt gives unrelated tags, which can't be compared with the others
+        let resalted = AddressSetCommitment::new([8u8; 32], &public_keys[0]);
+        assert!(resalted.is_well_formed());
+        assert!(resalted
+            .tags()
+            .iter()
+            .all(|tag| !first.tags().contains(tag)));
+        commitments.push(("org4".to_owned(), resalted.clone()));
+        commitments.push(("org5".to_owned(), resalted));
+        assert_eq!(
+            find_overlaps(&commitments),
+            Err(DisjointnessError::SaltMismatch(vec![
+                "org4".to_owned(),
+                "org5".to_owned()
+            ]))
+        );
+        // Every commitment under another salt fails, and the overlap among the others is still found
+        assert_eq!(
+            failed_disjointness(&commitments),
+            vec!["org1", "org3", "org4", "org5"]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use ark_ed_on_bn254::Fr as ScalarField;
+use ark_ff::Zero;
+use serde::{Deserialize, Serialize};
+
+use super::{pedersen::ValueCommitment, wire::hex};
+
+/// Value of one asset that entered or left the organizations through wallets outside all of them during an epoch.
+///
+/// The flow is the counterparty of every deposit and withdrawal, so its commitment is the negated sum of what the
+/// organizations added to their delta commitments for them. Deposits and withdrawals are public, which is why the
+/// flow is published opened, with its blinding.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExternalFlow {
+    deposits: i64,               // value external wallets sent to organizations
+    withdrawals: i64,            // value organizations sent to external wallets
+    commitment: ValueCommitment, // commitment to `withdrawals - deposits`
+    #[serde(with = "hex")]
+    blinding: ScalarField,
+}
+impl Default for ExternalFlow {
+    fn default() -> Self {
+        Self {
+            deposits: 0,
+            withdrawals: 0,
+            commitment: ValueCommitment::zero(),
+            blinding: ScalarField::zero(),
+        }
+    }
+}
+impl ExternalFlow {
+    /// Record value an external wallet sent to an organization, given the commitment the organization was credited
+    pub fn deposit(&mut self, value: i32, commitment: ValueCommitment, blinding: ScalarField) {
+        self.deposits += i64::from(value);
+        self.commitment = self.commitment - commitment;
+        self.blinding -= blinding;
+    }
+    /// Record value an organization sent to an external wallet, given the commitment the organization was debited
+    pub fn withdraw(&mut self, value: i32, commitment: ValueCommitment, blinding: ScalarField) {
+        self.withdrawals += i64::from(value);
+        self.commitment = self.commitment + commitment;
+        self.blinding += blinding;
+    }
+    pub fn deposits(&self) -> i64 {
+        self.deposits
+    }
+    pub fn withdrawals(&self) -> i64 {
+        self.withdrawals
+    }
+    /// Value that left the organizations, negative when more came in than went out
+    pub fn net_outflow(&self) -> i64 {
+        self.withdrawals - self.deposits
+    }
+    pub fn commitment(&self) -> ValueCommitment {
+        self.commitment
+    }
+    pub fn blinding(&self) -> ScalarField {
+        self.blinding
+    }
+    /// Whether the commitment opens to the public net outflow
+    pub fn is_consistent(&self) -> bool {
+        self.commitment.opens_to(self.net_outflow(), &self.blinding)
+    }
+}
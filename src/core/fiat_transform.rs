@@ -1,10 +1,8 @@
 use ark_ff::PrimeField;
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
-use ark_relations::r1cs::ConstraintSystem;
 use dusk_bls12_381::BlsScalar;
 use dusk_poseidon::{Domain, Hash};
 
-use crate::{gadgets::merkle_gadget::MerkleTreeGadget, utils::fpvar_to_u64};
+use crate::{gadgets::merkle_gadget::MerkleTreeGadget, utils::prime_field_to_u64};
 fn convert_scalars_to_single_u32(scalars: Vec<BlsScalar>) -> u32 {
     let mut total: u64 = 0;
 
@@ -21,13 +19,6 @@ fn convert_scalars_to_single_u32(scalars: Vec<BlsScalar>) -> u32 {
 /// The goal here is take the merkle tree roots and recreate the Merkle tree root. From there, we can apply a fiat-shamir style
 /// transform to it by hashing it with the Poseidon hash. This hashed result is then the alpha we can use in the asset proof.
 pub fn generate_alpha<F: PrimeField>(blockchain_merkle_roots: Vec<F>) -> u32 {
-    let cs = ConstraintSystem::<F>::new_ref();
-    let merkle_roots: Vec<FpVar<F>> = blockchain_merkle_roots
-        .into_iter()
-        .map(|field_element| {
-            FpVar::new_input(cs.clone(), || Ok(field_element)).expect("Failed to create FpVar")
-        })
-        .collect();
     // NOTE: It looks this snippet was a major bottleneck in performance and possibly memory allocations.
     //       In terms of performance when running with 100 transactions, 5 orgs and 10 addresses per org,
     //       we go from just under a minute for all proof gen and validation (excluding all setup and
@@ -35,17 +26,16 @@ pub fn generate_alpha<F: PrimeField>(blockchain_merkle_roots: Vec<F>) -> u32 {
     //       Running without this extra validation didn't seem to break anything so I am opting to keep
     //       this off.
 
-    // let cs = ConstraintSystem::<F>::new_ref();
-    // for (idx, _) in merkle_roots.iter().enumerate() {
+    // for (idx, _) in blockchain_merkle_roots.iter().enumerate() {
     //     // Verify the chosen leaf
     //     let is_valid =
-    //         MerkleTreeGadget::generate_proof_and_validate(&merkle_roots, cs.clone(), vec![idx]);
+    //         MerkleTreeGadget::generate_proof_and_validate(&blockchain_merkle_roots, vec![idx]);
     //     if !is_valid {
     //         panic!("Cannot get root hash if leaves are not all valid");
     //     }
     // }
-    let root = MerkleTreeGadget::create_root_hash(merkle_roots, cs);
-    let base = fpvar_to_u64(&root).unwrap();
+    let root = MerkleTreeGadget::create_root_hash(blockchain_merkle_roots);
+    let base = prime_field_to_u64(root).unwrap();
     let input = BlsScalar::from_raw([base, base, base, base]);
     let hash = Hash::digest(Domain::Other, &[input; 1]);
     convert_scalars_to_single_u32(hash)
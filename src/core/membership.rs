@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use ark_ed_on_bn254::EdwardsAffine;
+use serde::{Deserialize, Serialize};
+
+use super::{asset::AssetId, wire::hex};
+
+/// Why an organization couldn't join or leave the network
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MembershipError {
+    /// Another organization on the network already uses this identifier
+    DuplicateIdentifier(String),
+    /// One of the organization's addresses already belongs to an organization or an external wallet, or is listed twice
+    DuplicateAddress,
+    /// No organization on the network has this identifier
+    UnknownOrganization(String),
+    /// Organizations only join or leave between epochs, before the first block of the epoch and with no pending
+    /// transactions
+    NotAtEpochBoundary,
+    /// The organization collects the network's fees and can't leave
+    FeeSink(String),
+}
+impl Display for MembershipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateIdentifier(identifier) => {
+                write!(
+                    f,
+                    "organization {identifier} is already part of the network"
+                )
+            }
+            Self::DuplicateAddress => write!(f, "address is already part of the network"),
+            Self::UnknownOrganization(identifier) => {
+                write!(f, "organization {identifier} is not part of the network")
+            }
+            Self::NotAtEpochBoundary => {
+                write!(f, "organizations only join or leave between epochs")
+            }
+            Self::FeeSink(identifier) => {
+                write!(
+                    f,
+                    "organization {identifier} collects the fees and can't leave"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for MembershipError {}
+
+/// Identifiers of the organizations taking part in an epoch according to the membership `records`, in the order they
+/// joined
+pub fn members(records: &[MembershipRecord], epoch: usize) -> Vec<String> {
+    let mut members: Vec<String> = Vec::new();
+    for record in records.iter().filter(|record| record.epoch <= epoch) {
+        match record.change {
+            MembershipChange::Joined => members.push(record.identifier.clone()),
+            MembershipChange::Left => members.retain(|member| *member != record.identifier),
+        }
+    }
+    members
+}
+/// The key an organization registered for signing its postings when it joined, if it was a member in the epoch
+pub fn signing_key(
+    records: &[MembershipRecord],
+    identifier: &str,
+    epoch: usize,
+) -> Option<EdwardsAffine> {
+    let mut signing_key = None;
+    for record in records
+        .iter()
+        .filter(|record| record.identifier == identifier && record.epoch <= epoch)
+    {
+        signing_key = match record.change {
+            MembershipChange::Joined => record.signing_key,
+            MembershipChange::Left => None,
+        };
+    }
+    signing_key
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipChange {
+    Joined,
+    Left,
+}
+
+/// An organization joining or leaving the network, as recorded on the blockchain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipRecord {
+    identifier: String,
+    change: MembershipChange,
+    epoch: usize, // first epoch the organization takes part in, or doesn't anymore
+    height: u64,  // height of the first block of that epoch
+    balances: BTreeMap<AssetId, i64>, // balances the organization joined with or carried out
+    #[serde(with = "hex")]
+    signing_key: Option<EdwardsAffine>, // key the organization signs its bulletin board postings with, if it has an address
+}
+impl MembershipRecord {
+    pub fn new(
+        identifier: String,
+        change: MembershipChange,
+        epoch: usize,
+        height: u64,
+        balances: BTreeMap<AssetId, i64>,
+        signing_key: Option<EdwardsAffine>,
+    ) -> Self {
+        Self {
+            identifier,
+            change,
+            epoch,
+            height,
+            balances,
+            signing_key,
+        }
+    }
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn change(&self) -> MembershipChange {
+        self.change
+    }
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+    pub fn balances(&self) -> &BTreeMap<AssetId, i64> {
+        &self.balances
+    }
+    pub fn signing_key(&self) -> Option<&EdwardsAffine> {
+        self.signing_key.as_ref()
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+};
+
+use ark_ff::PrimeField;
+
+use super::transaction::SharedTransaction;
+
+/// Why a transaction was turned away from the mempool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MempoolError {
+    /// The sender's address isn't registered with any organization on the network
+    UnknownSender,
+    /// An output goes to an address no organization on the network owns
+    UnknownReceiver,
+    /// The signature doesn't verify against the key the sender registered with its organization
+    InvalidSignature,
+    /// The transaction spends the same serial number more than once
+    DuplicateSerialNumber,
+    /// An input note's commitment isn't on the blockchain
+    UnknownNote,
+    /// A serial number has already been spent on the blockchain
+    AlreadySpent,
+    /// A serial number is also spent by a transaction waiting in the mempool
+    ConflictsWithPending,
+    /// The transaction pays a fee but the network has no fee sink to credit it to
+    NoFeeSink,
+    /// An external wallet tried to spend out of a serial number pool rather than notes
+    NoInputNotes,
+    /// A transfer without input notes spends a serial number that isn't in the sender organization's unused pool
+    UnknownSerialNumber,
+    /// The fee doesn't fit a balance
+    ValueOutOfRange,
+    /// An output's range proof doesn't verify against its value commitment
+    InvalidRangeProof,
+    /// A transaction spending notes has no `JoinSplit` proof, or one that doesn't verify against the shared transaction:
+    /// the serial numbers aren't the nullifiers of the sender's notes, or the notes don't carry the value of the
+    /// outputs plus the fee
+    InvalidJoinSplitProof,
+}
+impl Display for MempoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownSender => write!(f, "sender address is not part of the network"),
+            Self::UnknownReceiver => write!(f, "receiver address is not part of the network"),
+            Self::InvalidSignature => {
+                write!(f, "signature does not verify against the sender's address")
+            }
+            Self::DuplicateSerialNumber => write!(f, "transaction spends the same note twice"),
+            Self::UnknownNote => {
+                write!(f, "transaction spends a note that is not on the blockchain")
+            }
+            Self::AlreadySpent => {
+                write!(f, "transaction spends a note that has already been spent")
+            }
+            Self::ConflictsWithPending => {
+                write!(f, "transaction conflicts with a pending transaction")
+            }
+            Self::NoFeeSink => write!(f, "transaction pays a fee but the network has no fee sink"),
+            Self::NoInputNotes => write!(f, "external wallets can only spend notes"),
+            Self::UnknownSerialNumber => {
+                write!(
+                    f,
+                    "serial number is not in the sender's pool of unused serial numbers"
+                )
+            }
+            Self::ValueOutOfRange => write!(f, "fee is too large"),
+            Self::InvalidRangeProof => write!(f, "output range proof does not verify"),
+            Self::InvalidJoinSplitProof => write!(f, "join-split proof does not verify"),
+        }
+    }
+}
+impl std::error::Error for MempoolError {}
+
+/// Pending transactions left out of a block, each with the reason it was dropped
+pub type DroppedTransactions<F> = Vec<(SharedTransaction<F>, MempoolError)>;
+
+/// Transactions that were accepted by the network but aren't on the blockchain yet.
+///
+/// Pending transactions are ordered by fee, highest first, and by arrival among equal fees. No two of them spend the
+/// same serial number, so they can be applied in any order.
+#[derive(Clone, Default)]
+pub struct Mempool<F: PrimeField> {
+    pending: BTreeMap<(Reverse<u64>, u64), SharedTransaction<F>>, // pending transactions keyed by (fee, arrival)
+    serial_numbers: HashSet<F>, // serial numbers spent by the pending transactions
+    arrivals: u64,              // number of transactions ever accepted, orders equal fees
+}
+impl<F> Mempool<F>
+where
+    F: PrimeField,
+{
+    pub fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            serial_numbers: HashSet::new(),
+            arrivals: 0,
+        }
+    }
+    /// Add a transaction without validating it, see `Network::submit_transaction`
+    pub fn insert(&mut self, t: SharedTransaction<F>) {
+        self.serial_numbers.extend(t.serial_numbers());
+        self.pending.insert((Reverse(t.fee()), self.arrivals), t);
+        self.arrivals += 1;
+    }
+    /// Remove and return up to `count` transactions in mempool order
+    pub fn take(&mut self, count: usize) -> Vec<SharedTransaction<F>> {
+        let mut taken = Vec::with_capacity(count.min(self.pending.len()));
+        while taken.len() < count {
+            let Some((_, t)) = self.pending.pop_first() else {
+                break;
+            };
+            for serial_number in t.serial_numbers() {
+                self.serial_numbers.remove(&serial_number);
+            }
+            taken.push(t);
+        }
+        taken
+    }
+    /// Whether a pending transaction spends this serial number
+    pub fn is_pending(&self, serial_number: &F) -> bool {
+        self.serial_numbers.contains(serial_number)
+    }
+    /// The pending transactions in mempool order
+    pub fn pending(&self) -> impl Iterator<Item = &SharedTransaction<F>> {
+        self.pending.values()
+    }
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        address::Address,
+        asset::AssetId,
+        network::{
+            fixtures::{submit, two_organization_network},
+            shared_range_setup,
+        },
+        note::Note,
+        org::SERIAL_NUMBERS_PER_ADDRESS,
+        signature::{self, message_from_field},
+        transaction::Transaction,
+    };
+    use ark_bn254::Fr;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_mempool_order() {
+        let (range_key, _) = shared_range_setup();
+        let sender = Address::new(Fr::from(1u64));
+        let receiver = Address::new(Fr::from(2u64));
+        let transaction = |rho: u64, fee: u64| {
+            Transaction::new(
+                Fr::from(rho),
+                10,
+                sender.clone(),
+                receiver.public_address(),
+                Fr::from(rho),
+                &range_key,
+            )
+            .with_fee(fee, &sender)
+            .shared()
+            .clone()
+        };
+        let mut mempool = Mempool::new();
+        mempool.insert(transaction(1, 0));
+        mempool.insert(transaction(2, 3));
+        mempool.insert(transaction(3, 0));
+        mempool.insert(transaction(4, 3));
+        assert_eq!(mempool.len(), 4);
+        let first = transaction(1, 0).serial_number();
+        assert!(mempool.is_pending(&first));
+
+        // Higher fees go first, equal fees keep their arrival order
+        let taken: Vec<_> = mempool
+            .take(3)
+            .iter()
+            .map(SharedTransaction::transaction_id)
+            .collect();
+        assert_eq!(taken, vec![Fr::from(2u64), Fr::from(4u64), Fr::from(1u64)]);
+        assert!(!mempool.is_pending(&first));
+        assert_eq!(mempool.take(3).len(), 1);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_validation() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let (sender, receiver) = (first_addresses[0].clone(), second_addresses[0].clone());
+        let note = network.mint_note(*sender.public_key(), AssetId::NATIVE, 100);
+        let spend = |tid: u64, value: i32| {
+            Transaction::spend_note(
+                Fr::from(tid),
+                &note,
+                value,
+                0,
+                sender.clone(),
+                receiver.public_address(),
+                &range_key,
+                &join_split_key,
+            )
+        };
+
+        // Accepted transactions wait in the mempool without touching the organizations or the blockchain
+        submit(&mut network, spend(1, 30)).unwrap();
+        submit(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                12,
+                receiver.clone(),
+                sender.public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        )
+        .unwrap();
+        assert_eq!(network.mempool().len(), 2);
+        assert_eq!(network.organizations()["org2"].delta(AssetId::NATIVE), 0);
+        assert_eq!(network.blockchain().note_commitments().len(), 1);
+
+        let conflicting = spend(3, 20);
+        assert_eq!(
+            submit(&mut network, conflicting.clone()),
+            Err(MempoolError::ConflictsWithPending)
+        );
+        let tampered: SharedTransaction<Fr> = serde_json::from_str(
+            &serde_json::to_string(conflicting.shared())
+                .unwrap()
+                .replacen("\"fee\":0", "\"fee\":1", 1),
+        )
+        .unwrap();
+        assert_eq!(
+            network.submit_transaction(tampered),
+            Err(MempoolError::InvalidSignature)
+        );
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::new(
+                    Fr::from(5u64),
+                    10,
+                    sender.clone(),
+                    Address::new(Fr::from(5u64)).public_address(),
+                    Fr::from(1u64),
+                    &range_key,
+                )
+            ),
+            Err(MempoolError::UnknownReceiver)
+        );
+        let unminted = Note::random(*sender.public_key(), AssetId::NATIVE, 10, &mut OsRng);
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::spend_note(
+                    Fr::from(6u64),
+                    &unminted,
+                    10,
+                    0,
+                    sender.clone(),
+                    receiver.public_address(),
+                    &range_key,
+                    &join_split_key,
+                )
+            ),
+            Err(MempoolError::UnknownNote)
+        );
+
+        // Producing a block applies the pending transactions, after which the note counts as spent on the blockchain
+        let (block, dropped) = network.produce_block();
+        assert_eq!(block.len(), 2);
+        assert!(dropped.is_empty());
+        assert!(network.mempool().is_empty());
+        assert_eq!(
+            network.organizations()["org2"].delta(AssetId::NATIVE),
+            30 - 12
+        );
+        assert_eq!(network.blockchain().note_commitments().len(), 4);
+        assert_eq!(
+            submit(&mut network, conflicting.clone()),
+            Err(MempoolError::AlreadySpent)
+        );
+
+        // Range proofs have to verify against the network's setup, and the receiver only keeps books with openings of
+        // its own outputs
+        let note = network.mint_note(*sender.public_key(), AssetId::NATIVE, 50);
+        let (foreign_key, _) = shared_range_setup();
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::spend_note(
+                    Fr::from(7u64),
+                    &note,
+                    10,
+                    0,
+                    sender.clone(),
+                    receiver.public_address(),
+                    &foreign_key,
+                    &join_split_key,
+                )
+            ),
+            Err(MempoolError::InvalidRangeProof)
+        );
+        let honest = Transaction::spend_note(
+            Fr::from(8u64),
+            &note,
+            10,
+            0,
+            sender.clone(),
+            receiver.public_address(),
+            &range_key,
+            &join_split_key,
+        );
+        let output = honest.outputs()[0].clone();
+        let mut mismatched = serde_json::to_value(&honest).unwrap();
+        mismatched["openings"] = serde_json::to_value(conflicting.openings()).unwrap();
+        network.send_openings(&serde_json::from_value(mismatched).unwrap());
+        assert!(network.organizations()["org2"].opening(&output).is_none());
+        submit(&mut network, honest).unwrap();
+        assert!(network.organizations()["org2"].opening(&output).is_some());
+    }
+
+    #[test]
+    fn test_mempool_rejects_foreign_serial_numbers() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let (sender, receiver) = (first_addresses[0].clone(), second_addresses[0].clone());
+
+        // A ρ outside the sender's pool gives a serial number the organization never had
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::new(
+                    Fr::from(1u64),
+                    10,
+                    sender.clone(),
+                    receiver.public_address(),
+                    Fr::from(SERIAL_NUMBERS_PER_ADDRESS),
+                    &range_key,
+                )
+            ),
+            Err(MempoolError::UnknownSerialNumber)
+        );
+
+        // A note spend revealing pool serial numbers instead of the note's nullifier, signed by the sender
+        let note = network.mint_note(*sender.public_key(), AssetId::NATIVE, 50);
+        let pool_transfer = Transaction::new(
+            Fr::from(2u64),
+            10,
+            sender.clone(),
+            receiver.public_address(),
+            Fr::from(3u64),
+            &range_key,
+        );
+        let mut forged = serde_json::to_value(
+            Transaction::spend_note(
+                Fr::from(3u64),
+                &note,
+                10,
+                0,
+                sender.clone(),
+                receiver.public_address(),
+                &range_key,
+                &join_split_key,
+            )
+            .shared(),
+        )
+        .unwrap();
+        forged["serial_numbers"] =
+            serde_json::to_value(pool_transfer.shared()).unwrap()["serial_numbers"].clone();
+        let root = serde_json::from_value::<SharedTransaction<Fr>>(forged.clone())
+            .unwrap()
+            .root();
+        forged["signature"] = serde_json::to_value(signature::sign(
+            sender.secret_key(),
+            message_from_field(&root),
+        ))
+        .unwrap();
+        let forged: SharedTransaction<Fr> = serde_json::from_value(forged).unwrap();
+        assert!(forged.verify_signature(sender.verification_key()));
+        assert_eq!(
+            network.submit_transaction(forged),
+            Err(MempoolError::InvalidJoinSplitProof)
+        );
+
+        // Paying a note too large for a balance back to the sender's own address, which no range proof covers
+        let large = network.mint_note(*sender.public_key(), AssetId::NATIVE, u64::from(u32::MAX));
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::join_split(
+                    Fr::from(4u64),
+                    sender.clone(),
+                    vec![large.clone()],
+                    vec![(sender.public_address(), large.value())],
+                    0,
+                    &range_key,
+                    &join_split_key,
+                )
+            ),
+            Err(MempoolError::InvalidRangeProof)
+        );
+
+        // None of them reached the mempool, so producing a block applies nothing
+        assert!(network.mempool().is_empty());
+        let (block, dropped) = network.produce_block();
+        assert!(block.is_empty() && dropped.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_rejects_inflation() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let (sender, receiver) = (first_addresses[0].clone(), second_addresses[0].clone());
+
+        // Sign a tampered transaction as the sender, so only the tampering itself can be caught
+        let sign = |mut forged: serde_json::Value| {
+            let root = serde_json::from_value::<SharedTransaction<Fr>>(forged.clone())
+                .unwrap()
+                .root();
+            forged["signature"] = serde_json::to_value(signature::sign(
+                sender.secret_key(),
+                message_from_field(&root),
+            ))
+            .unwrap();
+            let forged: SharedTransaction<Fr> = serde_json::from_value(forged).unwrap();
+            assert!(forged.verify_signature(sender.verification_key()));
+            forged
+        };
+
+        // Pay out 500 from a note worth 50: build the outputs against a made up note, then swap in the real note with
+        // its commitment and nullifier, so everything but the values checks out
+        let note = network.mint_note(*sender.public_key(), AssetId::NATIVE, 50);
+        let made_up = Note::random(*sender.public_key(), AssetId::NATIVE, 500, &mut OsRng);
+        let honest = serde_json::to_value(
+            Transaction::spend_note(
+                Fr::from(1u64),
+                &note,
+                50,
+                0,
+                sender.clone(),
+                receiver.public_address(),
+                &range_key,
+                &join_split_key,
+            )
+            .shared(),
+        )
+        .unwrap();
+        let mut forged = serde_json::to_value(
+            Transaction::join_split(
+                Fr::from(1u64),
+                sender.clone(),
+                vec![made_up],
+                vec![(receiver.public_address(), 500)],
+                0,
+                &range_key,
+                &join_split_key,
+            )
+            .shared(),
+        )
+        .unwrap();
+        for field in ["serial_numbers", "input_note_commitments"] {
+            forged[field] = honest[field].clone();
+        }
+        assert_eq!(
+            network.submit_transaction(sign(forged.clone())),
+            Err(MempoolError::InvalidJoinSplitProof)
+        );
+
+        // Neither dropping the proof nor bringing along the proof of the honest spend gets the outputs through
+        for proof in [
+            serde_json::Value::from("00"),
+            honest["join_split_proof"].clone(),
+        ] {
+            forged["join_split_proof"] = proof;
+            assert_eq!(
+                network.submit_transaction(sign(forged.clone())),
+                Err(MempoolError::InvalidJoinSplitProof)
+            );
+        }
+
+        // Relabelling the transaction moves the native note's value into another asset
+        let mut forged = honest;
+        forged["asset"] = serde_json::to_value(AssetId::new(7)).unwrap();
+        assert_eq!(
+            network.submit_transaction(sign(forged)),
+            Err(MempoolError::InvalidJoinSplitProof)
+        );
+
+        assert!(network.mempool().is_empty());
+        let (block, dropped) = network.produce_block();
+        assert!(block.is_empty() && dropped.is_empty());
+    }
+}
@@ -1,8 +1,22 @@
 pub mod address;
+pub mod asset;
+pub mod block;
 pub mod blockchain;
+pub mod bulletin_board;
+pub mod disjointness;
+pub mod external;
 pub mod fiat_transform;
+pub mod key_derivation;
+pub mod membership;
+pub mod mempool;
 pub mod network;
+pub mod network_state;
+pub mod note;
 pub mod org;
+pub mod pedersen;
 pub mod run_config;
 pub mod serial_number;
+pub mod signature;
 pub mod transaction;
+pub mod transaction_builder;
+pub mod wire;
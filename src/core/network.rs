@@ -1,17 +1,69 @@
-use ark_ff::PrimeField;
-use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, R1CSVar};
-use ark_relations::r1cs::ConstraintSystemRef;
-use std::{collections::HashMap, io::BufRead};
+use ark_bn254::{Bn254, Fr};
+use ark_crypto_primitives::snark::SNARK;
+use ark_ed_on_bn254::Fr as ScalarField;
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{r1cs_to_qap::LibsnarkReduction, Groth16, Proof, ProvingKey, VerifyingKey};
+use rand::rngs::OsRng;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::BufRead,
+};
 
-use crate::{analysis::estimate_vec_memory_usage_in_gb, utils::fpvars_to_u64s};
+use crate::{
+    analysis::estimate_vec_memory_usage_in_gb,
+    gadgets::{
+        aggregation::{aggregate_proofs, verify_aggregate_proof, AggregateProof},
+        asset_proof::AssetProof,
+        batch_verifier::batch_verify_or_find_invalid,
+        blockchain_validator::BlockchainValidatorCircuit,
+        epoch_circuit::{epoch_public_inputs, EpochBalanceCircuit},
+        join_split::JoinSplitCircuit,
+        value_commitment_gadget::ValueRangeCircuit,
+    },
+    utils::prime_fields_to_u64s,
+    verifier::{Auditor, EpochStatement, ProofSubmission},
+};
 
-use super::{blockchain::Blockchain, org::Organization, transaction::Transaction};
+use super::{
+    address::{Address, PublicAddress},
+    asset::AssetId,
+    block::Block,
+    blockchain::Blockchain,
+    bulletin_board::{
+        external_flows_hash, serial_number_root, BulletinBoard, BulletinBoardError, Posting,
+    },
+    disjointness::{failed_disjointness, AddressSetCommitment},
+    external::ExternalFlow,
+    membership::{MembershipChange, MembershipError, MembershipRecord},
+    mempool::{DroppedTransactions, Mempool, MempoolError},
+    note::Note,
+    org::Organization,
+    transaction::{SharedTransaction, Transaction, TransactionOutput},
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Maximum number of transactions per block unless set with [`Network::set_block_size`]
+pub const DEFAULT_BLOCK_SIZE: usize = 100;
 
 /// The `Network` type is a abstract representation of a cryptocurrency exchange (like FTX or Binance)
 #[derive(Clone)]
 pub struct Network<F: PrimeField> {
     organizations: HashMap<String, Organization<F>>, // Maps organization names to their instances
     blockchain: Blockchain<F>,                       // The blockchain where transactions are stored
+    address_index: HashMap<F, String>, // Maps address public keys to the name of the organization owning them
+    fee_sink: Option<String>,          // The organization transaction fees are credited to
+    external_wallets: HashMap<F, PublicAddress<F>>, // Wallets outside every organization, keyed by public key
+    external_flows: BTreeMap<AssetId, ExternalFlow>, // Value that entered or left the organizations this epoch
+    mempool: Mempool<F>,                             // Transactions waiting for the next block
+    bulletin_board: BulletinBoard<F>,                // What organizations published every epoch
+    block_size: usize,                               // Maximum number of transactions per block
+    block_interval: u64,                             // Simulated time steps between blocks
+    time: u64,                                       // Simulated clock
+    range_proving_key: ProvingKey<Bn254>, // Published so senders can prove their output values are in range
+    range_verifying_key: VerifyingKey<Bn254>, // Checks the range proofs of every transaction output
+    join_split_proving_key: ProvingKey<Bn254>, // Published so senders can prove the notes they spend
+    join_split_verifying_key: VerifyingKey<Bn254>, // Checks the JoinSplit proof of every transaction spending notes
 }
 impl<F> Default for Network<F>
 where
@@ -26,70 +78,489 @@ impl<F> Network<F>
 where
     F: PrimeField,
 {
+    /// A network with fresh setups for the output range proofs and the `JoinSplit` proofs
     pub fn new() -> Self {
+        Self::with_proof_keys(shared_range_setup(), shared_join_split_setup())
+    }
+    /// A network checking transaction proofs against existing setups, e.g. ones persisted with the network
+    pub fn with_proof_keys(
+        (range_proving_key, range_verifying_key): (ProvingKey<Bn254>, VerifyingKey<Bn254>),
+        (join_split_proving_key, join_split_verifying_key): (
+            ProvingKey<Bn254>,
+            VerifyingKey<Bn254>,
+        ),
+    ) -> Self {
         Self {
             organizations: HashMap::new(),
             blockchain: Blockchain::default(),
+            address_index: HashMap::new(),
+            fee_sink: None,
+            external_wallets: HashMap::new(),
+            external_flows: BTreeMap::new(),
+            mempool: Mempool::new(),
+            bulletin_board: BulletinBoard::default(),
+            block_size: DEFAULT_BLOCK_SIZE,
+            block_interval: 1,
+            time: 0,
+            range_proving_key,
+            range_verifying_key,
+            join_split_proving_key,
+            join_split_verifying_key,
         }
     }
+    /// The key senders prove the values of their transaction outputs are in range with
+    pub fn range_proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.range_proving_key
+    }
+    pub fn range_verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.range_verifying_key
+    }
+    /// The key senders prove the notes their transactions spend with
+    pub fn join_split_proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.join_split_proving_key
+    }
+    pub fn join_split_verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.join_split_verifying_key
+    }
 
+    /// Same as [`Self::register_organization`] but panics if the organization can't join
     pub fn add_organization(&mut self, org: Organization<F>) {
-        // TODO: How does this handle a repeat key? For right now it should panic if there is a repeat
-        self.organizations.insert(org.identifier().clone(), org);
+        if let Err(error) = self.register_organization(org) {
+            panic!("Couldn't add organization: {error}");
+        }
+    }
+    /// Let an organization join the network at an epoch boundary, with the balances it was created with
+    pub fn register_organization(&mut self, org: Organization<F>) -> Result<(), MembershipError> {
+        let identifier = org.identifier();
+        if self.organizations.contains_key(&identifier) {
+            return Err(MembershipError::DuplicateIdentifier(identifier));
+        }
+        let mut public_keys = HashSet::new();
+        for address in org.known_addresses() {
+            if self.is_known_address(address.public_key())
+                || !public_keys.insert(*address.public_key())
+            {
+                return Err(MembershipError::DuplicateAddress);
+            }
+        }
+        if !self.is_epoch_boundary() {
+            return Err(MembershipError::NotAtEpochBoundary);
+        }
+
+        self.record_membership_change(&org, MembershipChange::Joined);
+        for public_key in public_keys {
+            self.address_index.insert(public_key, identifier.clone());
+        }
+        self.organizations.insert(identifier, org);
+        Ok(())
+    }
+    /// Let an organization leave the network at an epoch boundary. It takes its balances, notes and addresses along
+    pub fn remove_organization(
+        &mut self,
+        identifier: &str,
+    ) -> Result<Organization<F>, MembershipError> {
+        let org = self
+            .organizations
+            .get(identifier)
+            .ok_or_else(|| MembershipError::UnknownOrganization(identifier.to_owned()))?;
+        if self.fee_sink.as_deref() == Some(identifier) {
+            return Err(MembershipError::FeeSink(identifier.to_owned()));
+        }
+        if !self.is_epoch_boundary() {
+            return Err(MembershipError::NotAtEpochBoundary);
+        }
+
+        let org = org.clone();
+        self.record_membership_change(&org, MembershipChange::Left);
+        self.address_index.retain(|_, owner| owner != identifier);
+        self.organizations.remove(identifier);
+        Ok(org)
+    }
+    fn record_membership_change(&mut self, org: &Organization<F>, change: MembershipChange) {
+        let record = MembershipRecord::new(
+            org.identifier(),
+            change,
+            self.blockchain.epoch(),
+            self.blockchain.height(),
+            org.available_balances(),
+            org.known_addresses()
+                .first()
+                .map(|address| *address.verification_key()),
+        );
+        self.blockchain.record_membership_change(record);
+    }
+    /// Whether the current epoch hasn't started yet: no block has been produced in it and nothing is pending
+    pub fn is_epoch_boundary(&self) -> bool {
+        self.mempool.is_empty()
+            && self
+                .blockchain
+                .epoch_blocks(self.blockchain.epoch())
+                .is_empty()
     }
 
-    pub fn forward_transaction(&mut self, t: Transaction<F>) {
-        let binding = t.sender_address();
-        let sender_key = binding.public_key();
-        let binding = t.receiver_address();
-        let receiver_key = binding.public_key();
-        let serial_number = t.serial_number();
+    pub fn organization(&self, identifier: &str) -> Option<&Organization<F>> {
+        self.organizations.get(identifier)
+    }
+    /// Look up the organization owning the address with the given public key
+    pub fn organization_of(&self, address_public_key: &F) -> Option<&Organization<F>> {
+        self.address_index
+            .get(address_public_key)
+            .and_then(|identifier| self.organizations.get(identifier))
+    }
+
+    /// Credit transaction fees to the organization with the given identifier, e.g. the network operator
+    pub fn set_fee_sink(&mut self, identifier: &str) {
+        if !self.organizations.contains_key(identifier) {
+            panic!("Fee sink organization {identifier} is not part of the network");
+        }
+        self.fee_sink = Some(identifier.to_owned());
+    }
+    pub fn fee_sink(&self) -> Option<&String> {
+        self.fee_sink.as_ref()
+    }
+    /// Let a wallet outside every organization, e.g. a customer's own wallet, deposit into and withdraw from them
+    pub fn register_external_wallet(&mut self, address: PublicAddress<F>) {
+        if self.address_index.contains_key(address.public_key()) {
+            panic!("Address belongs to an organization");
+        }
+        self.external_wallets.insert(*address.public_key(), address);
+    }
+    pub fn is_external_wallet(&self, public_key: &F) -> bool {
+        self.external_wallets.contains_key(public_key)
+    }
+    /// Whether the address belongs to an organization or is a registered external wallet
+    pub fn is_known_address(&self, public_key: &F) -> bool {
+        self.address_index.contains_key(public_key) || self.is_external_wallet(public_key)
+    }
+    /// Deposits and withdrawals of `asset` through external wallets in the current epoch
+    pub fn external_flow(&self, asset: AssetId) -> ExternalFlow {
+        self.external_flows.get(&asset).cloned().unwrap_or_default()
+    }
+
+    /// Hand the openings of a transaction's outputs to the organizations that learn them from the sender, outside the
+    /// network: the sender's organization keeps every opening, a receiving organization gets the openings of its own
+    /// outputs. Each organization books the transaction with its own openings once it is applied.
+    pub fn send_openings(&mut self, t: &Transaction<F>) {
+        let sender_org = self.address_index.get(t.sender_address().public_key());
+        for opening in t.openings() {
+            let receiver_org = self.address_index.get(opening.note().owner_public_key());
+            for identifier in sender_org
+                .into_iter()
+                .chain(receiver_org.filter(|receiver_org| Some(*receiver_org) != sender_org))
+            {
+                let org = self.organizations.get_mut(identifier).unwrap();
+                org.receive_opening(opening.clone());
+            }
+        }
+    }
+    /// Validate a transaction and queue it in the mempool. It is only applied once a block is produced.
+    ///
+    /// The network only sees the shared transaction. The parties learn the openings of the outputs from the sender,
+    /// see [`Self::send_openings`].
+    pub fn submit_transaction(&mut self, t: SharedTransaction<F>) -> Result<(), MempoolError> {
+        self.validate_transaction(&t)?;
+        self.mempool.insert(t);
+        Ok(())
+    }
+    /// Validate a transaction and put it on the blockchain right away in a block of its own, bypassing the mempool
+    pub fn forward_transaction(&mut self, t: SharedTransaction<F>) {
+        if let Err(error) = self.validate_transaction(&t) {
+            panic!("Invalid transaction: {error}");
+        }
+        self.seal_block(vec![t]);
+    }
+    /// Maximum number of pending transactions that go into one block
+    pub fn set_block_size(&mut self, block_size: usize) {
+        assert!(
+            block_size > 0,
+            "Blocks have to hold at least one transaction"
+        );
+        self.block_size = block_size;
+    }
+    /// Number of simulated time steps between two blocks produced by [`Self::advance_time`]
+    pub fn set_block_interval(&mut self, block_interval: u64) {
+        assert!(
+            block_interval > 0,
+            "Block interval has to be at least one time step"
+        );
+        self.block_interval = block_interval;
+    }
+    /// Advance the simulated clock, producing a block every block interval.
+    ///
+    /// Returns the blocks that were produced, each with the pending transactions it dropped.
+    pub fn advance_time(&mut self, steps: u64) -> Vec<(Block<F>, DroppedTransactions<F>)> {
+        let mut blocks = Vec::new();
+        for _ in 0..steps {
+            self.time += 1;
+            if self.time.is_multiple_of(self.block_interval) {
+                blocks.push(self.produce_block());
+            }
+        }
+        blocks
+    }
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+    /// Take up to a block size of pending transactions in mempool order and put them on the blockchain as the next
+    /// block. Transactions that became invalid while they were pending are dropped.
+    ///
+    /// Returns the block alongside the dropped transactions and why each of them was dropped.
+    pub fn produce_block(&mut self) -> (Block<F>, DroppedTransactions<F>) {
+        let pending = self.mempool.take(self.block_size);
+        let mut transactions = Vec::with_capacity(pending.len());
+        let mut dropped = Vec::new();
+        for t in pending {
+            match self.validate_transaction(&t) {
+                Ok(()) => transactions.push(t),
+                Err(error) => dropped.push((t, error)),
+            }
+        }
+        (self.seal_block(transactions), dropped)
+    }
+    /// Link a block of validated transactions to the latest block, and only then apply the transactions
+    fn seal_block(&mut self, transactions: Vec<SharedTransaction<F>>) -> Block<F> {
+        let block = Block::new(
+            self.blockchain.height(),
+            self.time,
+            self.blockchain.latest_block_hash(),
+            transactions.iter().map(SharedTransaction::root).collect(),
+        );
+        self.blockchain.append_block(block.clone());
+        for t in transactions {
+            self.apply_transaction(t);
+        }
+        block
+    }
+    pub fn mempool(&self) -> &Mempool<F> {
+        &self.mempool
+    }
+    /// Check a transaction can be applied on top of the blockchain and the pending transactions
+    pub fn validate_transaction(&self, t: &SharedTransaction<F>) -> Result<(), MempoolError> {
+        let serial_numbers = t.serial_numbers();
+        if serial_numbers.iter().collect::<HashSet<_>>().len() != serial_numbers.len() {
+            return Err(MempoolError::DuplicateSerialNumber);
+        }
+        // The sender has to have authorized the spend. Check against the key the sender registered with its organization
+        // or the network so a transaction can't bring its own key along
+        let sender_public_key = *t.sender_address().public_key();
+        let sender = self
+            .organization_of(&sender_public_key)
+            .and_then(|org| org.address(&sender_public_key))
+            .map(Address::public_address)
+            .or_else(|| self.external_wallets.get(&sender_public_key).cloned())
+            .ok_or(MempoolError::UnknownSender)?;
+        if !t.verify_signature(sender.verification_key()) {
+            return Err(MempoolError::InvalidSignature);
+        }
+        // Only organizations hold serial number pools, external wallets spend notes
+        if self.is_external_wallet(&sender_public_key) && t.input_note_commitments().is_empty() {
+            return Err(MempoolError::NoInputNotes);
+        }
+        if t.outputs()
+            .iter()
+            .any(|output| !self.is_known_address(output.owner_public_key()))
+        {
+            return Err(MempoolError::UnknownReceiver);
+        }
+        if t.fee() > 0 && self.fee_sink.is_none() {
+            return Err(MempoolError::NoFeeSink);
+        }
+        // The range proofs show every output value is in range and fits a balance, without opening the outputs
+        if !t.verify_range_proofs(&self.range_verifying_key) {
+            return Err(MempoolError::InvalidRangeProof);
+        }
+        // A note can only be spent if it exists on the blockchain and neither the blockchain nor a pending transaction
+        // has spent it before
+        if t.input_note_commitments()
+            .iter()
+            .any(|commitment| !self.blockchain.has_note_commitment(commitment))
+        {
+            return Err(MempoolError::UnknownNote);
+        }
+        if serial_numbers.iter().any(|sn| self.blockchain.is_spent(sn)) {
+            return Err(MempoolError::AlreadySpent);
+        }
+        if serial_numbers.iter().any(|sn| self.mempool.is_pending(sn)) {
+            return Err(MempoolError::ConflictsWithPending);
+        }
+        // Spending has to go through the sender's own serial numbers: the unused pool of its organization, or the
+        // nullifiers of notes it owns. The JoinSplit proof shows the latter, and that the notes carry the value of the
+        // outputs plus the fee, so no transaction mints value or moves another asset
+        if t.input_note_commitments().is_empty() {
+            let unused = self
+                .organization_of(&sender_public_key)
+                .map(Organization::unused_serial_numbers)
+                .unwrap_or_default();
+            if serial_numbers.iter().any(|sn| !unused.contains(sn)) {
+                return Err(MempoolError::UnknownSerialNumber);
+            }
+        }
+        if !t.verify_join_split_proof(&self.join_split_verifying_key) {
+            return Err(MempoolError::InvalidJoinSplitProof);
+        }
+        if i32::try_from(t.fee()).is_err() {
+            return Err(MempoolError::ValueOutOfRange);
+        }
+        Ok(())
+    }
+    /// Update the organizations and the blockchain with a transaction. It can't fail on a transaction that passed
+    /// [`Self::validate_transaction`], which rules out every case the organizations' bookkeeping panics on.
+    ///
+    /// Every organization books the outputs it holds the openings of, see [`Self::send_openings`]. Value moving through
+    /// an external wallet is booked by the organization on the other side, the one that knows the amount.
+    fn apply_transaction(&mut self, t: SharedTransaction<F>) {
+        let serial_numbers = t.serial_numbers();
+        let input_note_commitments = t.input_note_commitments();
         let root = t.root();
-        let value = t.value();
+        let asset = t.asset();
+        let fee = i32::try_from(t.fee()).expect("Fee is too large");
+        // External wallets have no organization, value they send or receive moves through the external flow
+        let sender_org = self
+            .address_index
+            .get(t.sender_address().public_key())
+            .cloned();
+        let fee_sink = (fee > 0).then(|| self.fee_sink.clone().unwrap());
+        let opened = |identifier: Option<&String>, output: &TransactionOutput<F>| {
+            identifier
+                .and_then(|identifier| self.organizations[identifier].opening(output))
+                .map(|opening| (opening.value(), opening.value_blinding()))
+        };
 
-        // Forward the transaction to all organizations
-        for org in self.organizations.values_mut() {
-            if org.is_involved(&t) {
-                // println!("HERE");
-                // pause_until_enter();
-                // Check if the organization has either the sender or receiver address
-                let has_receiver = org.has_address(receiver_key);
-                let has_sender = org.has_address(sender_key);
-
-                if has_receiver {
-                    org.add_serial_number(serial_number.clone());
-                    org.update_delta(value);
+        // Each output goes to the organization owning its receiving address, the fee goes to the fee sink. Value moving
+        // between addresses of one organization, a self-transfer or a fee the sender's organization collects itself
+        // never leaves the organization and leaves its delta alone. The sending and the receiving side each know the
+        // value and blinding of an output from their own opening, everyone knows the fee
+        let transfers = t
+            .outputs()
+            .iter()
+            .map(|output| {
+                let receiver_org = self.address_index.get(output.owner_public_key()).cloned();
+                let sent = opened(sender_org.as_ref(), output);
+                let received = opened(receiver_org.as_ref(), output);
+                (receiver_org, output.value_commitment(), sent, received)
+            })
+            .chain(fee_sink.map(|identifier| {
+                let opening = Some((fee, ScalarField::zero()));
+                (Some(identifier), t.fee_commitment(), opening, opening)
+            }))
+            .filter(|(receiver_org, ..)| *receiver_org != sender_org)
+            .collect::<Vec<_>>();
+
+        // Every organization taking part caches the root and the serial numbers once
+        let mut involved_orgs = Vec::new();
+        if let Some(identifier) = sender_org {
+            let org = self.organizations.get_mut(&identifier).unwrap();
+            for serial_number in &serial_numbers {
+                if input_note_commitments.is_empty() {
+                    org.spend_serial_number(*serial_number, asset);
+                } else {
+                    org.consume_note(serial_number);
+                    org.add_serial_number(*serial_number, asset);
                 }
-                if has_sender {
-                    org.add_serial_number(serial_number.clone());
-                    org.update_delta(-value);
+            }
+            for (_, commitment, sent, _) in &transfers {
+                if let Some((value, blinding)) = sent {
+                    org.update_delta(asset, -value);
+                    org.update_delta_commitment(asset, -*commitment, -*blinding);
                 }
-
-                // Add the root only once if either receiver or sender is involved
-                if has_receiver || has_sender {
-                    org.add_root(root.clone());
+            }
+            org.add_root(root);
+            involved_orgs.push(identifier);
+        } else {
+            let flow = self.external_flows.entry(asset).or_default();
+            for (_, commitment, _, received) in &transfers {
+                if let Some((value, blinding)) = received {
+                    flow.deposit(*value, *commitment, *blinding);
                 }
             }
         }
+        for (identifier, commitment, sent, received) in transfers {
+            // Between two external wallets no organization knows the value, and none of it enters or leaves them
+            let Some(identifier) = identifier else {
+                if let Some((value, blinding)) = sent {
+                    self.external_flows
+                        .entry(asset)
+                        .or_default()
+                        .withdraw(value, commitment, blinding);
+                }
+                continue;
+            };
+            let org = self.organizations.get_mut(&identifier).unwrap();
+            if let Some((value, blinding)) = received {
+                org.update_delta(asset, value);
+                org.update_delta_commitment(asset, commitment, blinding);
+            }
+            if !involved_orgs.contains(&identifier) {
+                for serial_number in &serial_numbers {
+                    org.add_serial_number(*serial_number, asset);
+                }
+                org.add_root(root);
+                involved_orgs.push(identifier);
+            }
+        }
 
-        // Add the transaction to the blockchain
-        self.blockchain.append_transaction(root, serial_number);
+        // Add the transaction and its new notes to the blockchain. The organization owning an output takes the note
+        // out of its opening
+        self.blockchain
+            .append_transaction(root, &serial_numbers, asset, t.value_commitment());
+        for output in t.outputs() {
+            self.blockchain
+                .append_note_commitment(output.note_commitment());
+            if let Some(org) = self
+                .address_index
+                .get(output.owner_public_key())
+                .and_then(|identifier| self.organizations.get_mut(identifier))
+            {
+                if let Some(note) = org.opening(output).map(|opening| opening.note().clone()) {
+                    org.receive_note(note);
+                }
+            }
+        }
+    }
+    /// Create a note out of thin air, e.g. to fund an address at the start of a simulation
+    pub fn mint_note(&mut self, owner_public_key: F, asset: AssetId, value: u64) -> Note<F> {
+        let note = Note::random(owner_public_key, asset, value, &mut OsRng);
+        self.add_note(note.clone());
+        note
+    }
+    /// Put the note's commitment on the blockchain and deliver the note to the organization owning its address
+    fn add_note(&mut self, note: Note<F>) {
+        self.blockchain.append_note_commitment(note.commitment());
+        if let Some(org) = self
+            .address_index
+            .get(note.owner_public_key())
+            .and_then(|identifier| self.organizations.get_mut(identifier))
+        {
+            org.receive_note(note);
+        }
     }
 
     pub fn dump_network_info(&self) {
         for org in self.organizations.values() {
             org.dump_info();
         }
+        for (asset, flow) in &self.external_flows {
+            println!(
+                "External flow of {asset}: {} deposited, {} withdrawn",
+                flow.deposits(),
+                flow.withdrawals()
+            );
+        }
     }
+    /// Start a new epoch, the blocks produced so far make up the epoch that ends. Every organization starts the new
+    /// epoch with the balances it ended the last one with
     pub fn clean_deltas_and_balances_at_epoch_end(&mut self) {
         for org in self.organizations.values_mut() {
-            org.clear_delta();
-            org.clear_final_balance();
+            org.start_next_epoch();
         }
+        self.external_flows.clear();
+        self.blockchain.end_epoch();
     }
     pub fn transfer_delta_to_organization_balance(&mut self) {
         for org in self.organizations.values_mut() {
-            org.update_balance(org.delta());
+            for asset in org.assets() {
+                org.update_balance(asset, org.delta(asset));
+            }
         }
     }
     pub fn validate_all_epoch_deltas_and_final_balances(&mut self) {
@@ -104,39 +575,430 @@ where
             "Blockchain Values Vector Size for org is {:.6} GB",
             estimate_vec_memory_usage_in_gb(&blockchain_values)
         );
+        let salt = self.address_set_salt();
         // pause_until_enter();
-        for org in self.organizations.values_mut() {
-            org.validate_components(blockchain_keys.clone(), blockchain_values.clone());
+        #[cfg(not(feature = "parallel"))]
+        for org in self.organizations.values() {
+            org.validate_components(salt, &blockchain_keys, &blockchain_values);
         }
+        // Prove the organizations concurrently against the shared (read-only) blockchain data
+        #[cfg(feature = "parallel")]
+        self.organizations.par_iter().for_each(|(_, org)| {
+            org.validate_components(salt, &blockchain_keys, &blockchain_values);
+        });
     }
+    /// Every organization proves its assets separately for each asset it holds, against that asset's transactions
     pub fn validate_all_assets(&mut self) {
+        let validate = |org: &Organization<F>| {
+            for asset in org.assets() {
+                println!("Validating {} for org: {:?}", asset, org.identifier());
+                let (blockchain_keys, blockchain_values) = self.blockchain_of_asset(asset);
+                org.validate_assets(
+                    &blockchain_keys,
+                    prime_fields_to_u64s(org.serial_numbers_of(asset)),
+                    &blockchain_values,
+                );
+            }
+        };
+        #[cfg(not(feature = "parallel"))]
+        self.organizations.values().for_each(validate);
+        #[cfg(feature = "parallel")]
+        self.organizations
+            .par_iter()
+            .for_each(|(_, org)| validate(org));
+    }
+    /// The serial numbers and roots of the transactions moving `asset`, in matching order
+    fn blockchain_of_asset(&self, asset: AssetId) -> (Vec<F>, Vec<F>) {
+        self.blockchain.inner_of_asset(asset).into_iter().unzip()
+    }
+    /// Same as [`Self::validate_all_epoch_deltas_and_final_balances`] but every organization proves against one
+    /// shared setup per circuit so that all proofs of a circuit can be checked with a single batched multi-pairing.
+    /// If a batch is rejected each proof is re-verified on its own to single out the failing organizations.
+    ///
+    /// Returns the identifiers of the organizations whose proofs failed (an empty vector means everything verified).
+    pub fn batch_validate_all_epoch_deltas_and_final_balances(&mut self) -> Vec<String> {
         let blockchain_keys: Vec<F> = self.blockchain.inner().into_keys().collect();
         let blockchain_values: Vec<F> = self.blockchain.inner().into_values().collect();
-        for org in self.organizations.values_mut() {
-            println!("Validating assets for org: {:?}", org.identifier());
-            org.validate_assets(
-                blockchain_keys.clone(),
-                fpvars_to_u64s(org.serial_numbers()),
-                blockchain_values.clone(),
-            );
+
+        let salt = self.address_set_salt();
+        let (epoch_proving_key, epoch_verifying_key) = shared_epoch_setup();
+        let (validator_proving_key, validator_verifying_key) = shared_validator_setup();
+
+        let mut identifiers = Vec::with_capacity(self.organizations.len());
+        let mut epoch_proofs = Vec::with_capacity(self.organizations.len());
+        let mut validator_proofs = Vec::with_capacity(self.organizations.len());
+        let mut epoch_identifiers = Vec::new();
+        for org in self.organizations.values() {
+            identifiers.push(org.identifier());
+            for asset in org.assets() {
+                epoch_identifiers.push(org.identifier());
+                epoch_proofs.push((
+                    org.generate_epoch_proof(asset, salt, &epoch_proving_key),
+                    epoch_public_inputs(
+                        &org.delta_commitment(asset),
+                        org.address_set_commitment(salt).root(),
+                    ),
+                ));
+            }
+            validator_proofs.push((
+                org.generate_blockchain_validator_proof(
+                    &blockchain_keys,
+                    &blockchain_values,
+                    &validator_proving_key,
+                ),
+                vec![],
+            ));
         }
+
+        let mut failed: Vec<String> =
+            batch_verify_or_find_invalid(&epoch_verifying_key, &epoch_proofs)
+                .into_iter()
+                .map(|index| epoch_identifiers[index].clone())
+                .collect();
+        failed.extend(
+            batch_verify_or_find_invalid(&validator_verifying_key, &validator_proofs)
+                .into_iter()
+                .map(|index| identifiers[index].clone()),
+        );
+        failed.sort_unstable();
+        failed.dedup();
+        println!("Batch verified {} organizations", identifiers.len());
+        failed
     }
-    pub fn validate_no_zombie_serial_numbers(&mut self, cs: ConstraintSystemRef<F>) {
-        // Convert all blockchain keys to FpVar<F> first
-        let blockchain_keys: Vec<FpVar<F>> = self
-            .blockchain
-            .inner()
-            .into_keys()
-            .map(|key| FpVar::new_input(cs.clone(), || Ok(key)).unwrap()) // Create FpVar<F> for each key
+    /// Same as [`Self::validate_all_assets`] but batch verifies every organization's asset proofs.
+    ///
+    /// Returns the identifiers of the organizations whose proofs failed (an empty vector means everything verified).
+    pub fn batch_validate_all_assets(&mut self) -> Vec<String> {
+        let (proving_key, verifying_key) = shared_asset_setup();
+
+        let mut identifiers = Vec::with_capacity(self.organizations.len());
+        let mut proofs = Vec::with_capacity(self.organizations.len());
+        for org in self.organizations.values() {
+            for asset in org.assets() {
+                let (blockchain_keys, blockchain_values) = self.blockchain_of_asset(asset);
+                identifiers.push(org.identifier());
+                proofs.push((
+                    org.generate_asset_proof(
+                        asset,
+                        &blockchain_keys,
+                        &blockchain_values,
+                        &proving_key,
+                    ),
+                    vec![],
+                ));
+            }
+        }
+
+        let mut failed: Vec<String> = batch_verify_or_find_invalid(&verifying_key, &proofs)
+            .into_iter()
+            .map(|index| identifiers[index].clone())
+            .collect();
+        failed.dedup();
+        failed
+    }
+    /// Aggregate every organization's epoch proofs, one per asset, into a single [`AggregateProof`].
+    ///
+    /// Returns the verifying key shared by all the aggregated proofs alongside the aggregate so it can be checked
+    /// with [`Self::verify_aggregate_epoch_proof`].
+    pub fn aggregate_epoch_proofs(&self) -> (VerifyingKey<Bn254>, AggregateProof) {
+        let (proving_key, verifying_key) = shared_epoch_setup();
+        let proofs: Vec<Proof<Bn254>> = self
+            .organization_assets()
+            .map(|(org, asset)| {
+                org.generate_epoch_proof(asset, self.address_set_salt(), &proving_key)
+            })
+            .collect();
+        let aggregate = aggregate_proofs(&verifying_key, &proofs, &self.epoch_public_inputs());
+        (verifying_key, aggregate)
+    }
+    /// Aggregate every organization's asset proofs, one per asset, into a single [`AggregateProof`].
+    pub fn aggregate_asset_proofs(&self) -> (VerifyingKey<Bn254>, AggregateProof) {
+        let (proving_key, verifying_key) = shared_asset_setup();
+        let proofs: Vec<Proof<Bn254>> = self
+            .organization_assets()
+            .map(|(org, asset)| {
+                let (blockchain_keys, blockchain_values) = self.blockchain_of_asset(asset);
+                org.generate_asset_proof(asset, &blockchain_keys, &blockchain_values, &proving_key)
+            })
             .collect();
+        let aggregate = aggregate_proofs(&verifying_key, &proofs, &vec![vec![]; proofs.len()]);
+        (verifying_key, aggregate)
+    }
+    /// Check an aggregate produced by [`Self::aggregate_epoch_proofs`] against every organization's delta commitment
+    /// with a single verifier call.
+    pub fn verify_aggregate_epoch_proof(
+        &self,
+        verifying_key: &VerifyingKey<Bn254>,
+        aggregate: &AggregateProof,
+    ) -> bool {
+        verify_aggregate_proof(verifying_key, &self.epoch_public_inputs(), aggregate)
+    }
+    /// Check an aggregate produced by [`Self::aggregate_asset_proofs`] with a single verifier call. The asset circuit
+    /// takes no public inputs.
+    pub fn verify_aggregate_asset_proof(
+        &self,
+        verifying_key: &VerifyingKey<Bn254>,
+        aggregate: &AggregateProof,
+    ) -> bool {
+        let public_inputs = vec![vec![]; aggregate.proof_count()];
+        verify_aggregate_proof(verifying_key, &public_inputs, aggregate)
+    }
+    /// The public inputs of every epoch proof, i.e. each organization's delta commitment per asset and the root of its
+    /// address set
+    fn epoch_public_inputs(&self) -> Vec<Vec<Fr>> {
+        let salt = self.address_set_salt();
+        self.organization_assets()
+            .map(|(org, asset)| {
+                epoch_public_inputs(
+                    &org.delta_commitment(asset),
+                    org.address_set_commitment(salt).root(),
+                )
+            })
+            .collect()
+    }
+    /// The salt address sets are committed under, the latest block hash, so the tags change whenever a block is added
+    fn address_set_salt(&self) -> [u8; 32] {
+        self.blockchain.latest_block_hash()
+    }
+    /// Every organization paired with each asset it holds, in a stable order
+    fn organization_assets(&self) -> impl Iterator<Item = (&Organization<F>, AssetId)> {
+        self.organizations
+            .values()
+            .flat_map(|org| org.assets().into_iter().map(move |asset| (org, asset)))
+    }
+    /// Have every organization publish the commitment to its epoch delta in each asset and the commitment to its address
+    /// set on the blockchain, alongside the external flows. This has to happen before the deltas are cleared at the end
+    /// of the epoch.
+    pub fn publish_delta_commitments(&mut self) {
+        let salt = self.address_set_salt();
+        for org in self.organizations.values() {
+            self.blockchain
+                .publish_address_set_commitment(org.identifier(), org.address_set_commitment(salt));
+            for asset in org.assets() {
+                self.blockchain.publish_delta_commitment(
+                    org.identifier(),
+                    asset,
+                    org.delta_commitment(asset),
+                );
+            }
+        }
+        for (asset, flow) in &self.external_flows {
+            self.blockchain.publish_external_flow(*asset, flow.clone());
+        }
+    }
+    /// Public check that the published delta commitments of every asset add up to a commitment to zero, i.e. no money
+    /// was created or destroyed across the network, without learning any organization's delta.
+    ///
+    /// Every transaction adds its commitment to the receiver's delta commitment and subtracts it from the sender's, so
+    /// the blindings cancel pairwise and the sum has to be the identity, a commitment to zero with zero blinding.
+    /// Deposits and withdrawals cancel against the published external flows, which have to open to their public
+    /// amounts.
+    pub fn verify_zero_sum(&self) -> bool {
+        let statement = EpochStatement::from_blockchain(&self.blockchain, self.blockchain.epoch());
+        self.organization_assets().all(|(org, asset)| {
+            statement
+                .delta_commitment(&org.identifier(), asset)
+                .is_some()
+        }) && statement.is_zero_sum()
+    }
+    /// Have every organization prove each asset it holds against one shared setup per circuit, and hand the proofs
+    /// out together with an [`Auditor`] that only knows the verifying keys. Check them with [`Auditor::audit`] against
+    /// the published [`EpochStatement`].
+    pub fn prepare_audit(&self) -> (Auditor, Vec<ProofSubmission>) {
+        let identifiers: Vec<String> = self.organizations.keys().cloned().collect();
+        self.prepare_audit_of(&identifiers)
+    }
+    /// Same as [`Self::prepare_audit`] but only the organizations in `identifiers` prove
+    pub fn prepare_audit_of(&self, identifiers: &[String]) -> (Auditor, Vec<ProofSubmission>) {
+        let (epoch_proving_key, epoch_verifying_key) = shared_epoch_setup();
+        let (asset_proving_key, asset_verifying_key) = shared_asset_setup();
+        (
+            Auditor::new(epoch_verifying_key, asset_verifying_key),
+            self.prove_epochs(identifiers, &epoch_proving_key, &asset_proving_key),
+        )
+    }
+    /// Have the organizations in `identifiers` prove every asset's epoch with keys from an earlier
+    /// [`shared_epoch_setup`] and [`shared_asset_setup`], so an auditor holding the verifying keys can check them
+    pub fn prove_epochs(
+        &self,
+        identifiers: &[String],
+        epoch_proving_key: &ProvingKey<Bn254>,
+        asset_proving_key: &ProvingKey<Bn254>,
+    ) -> Vec<ProofSubmission> {
+        self.organization_assets()
+            .filter(|(org, _)| identifiers.contains(&org.identifier()))
+            .map(|(org, asset)| {
+                let (blockchain_keys, blockchain_values) = self.blockchain_of_asset(asset);
+                ProofSubmission::new(
+                    org.identifier(),
+                    asset,
+                    org.generate_epoch_proof(asset, self.address_set_salt(), epoch_proving_key),
+                    org.generate_asset_proof(
+                        asset,
+                        &blockchain_keys,
+                        &blockchain_values,
+                        asset_proving_key,
+                    ),
+                )
+            })
+            .collect()
+    }
+    /// Have the organizations in `identifiers` prove their transactions against the blockchain, against one shared
+    /// setup. The validator circuit takes no public inputs, so the proofs are checked with the verifying key alone.
+    pub fn prove_blockchain_validity(
+        &self,
+        identifiers: &[String],
+    ) -> (VerifyingKey<Bn254>, Vec<(String, Proof<Bn254>)>) {
+        let (proving_key, verifying_key) = shared_validator_setup();
+        (
+            verifying_key,
+            self.prove_blockchain_validity_with(identifiers, &proving_key),
+        )
+    }
+    /// Same as [`Self::prove_blockchain_validity`] with a proving key from an earlier [`shared_validator_setup`]
+    pub fn prove_blockchain_validity_with(
+        &self,
+        identifiers: &[String],
+        proving_key: &ProvingKey<Bn254>,
+    ) -> Vec<(String, Proof<Bn254>)> {
+        let blockchain_keys: Vec<F> = self.blockchain.inner().into_keys().collect();
+        let blockchain_values: Vec<F> = self.blockchain.inner().into_values().collect();
+        identifiers
+            .iter()
+            .filter_map(|identifier| self.organizations.get(identifier))
+            .map(|org| {
+                (
+                    org.identifier(),
+                    org.generate_blockchain_validator_proof(
+                        &blockchain_keys,
+                        &blockchain_values,
+                        proving_key,
+                    ),
+                )
+            })
+            .collect()
+    }
+    /// Check that every organization's epoch proof was made for the delta commitment and address set it published.
+    ///
+    /// Returns the identifiers of the organizations whose proofs don't verify against their published commitment
+    /// (including organizations that never published one).
+    pub fn verify_published_delta_commitments(&self) -> Vec<String> {
+        let (proving_key, verifying_key) = shared_epoch_setup();
+        let mut identifiers = Vec::with_capacity(self.organizations.len());
+        let mut proofs = Vec::with_capacity(self.organizations.len());
+        let mut unpublished = Vec::new();
+        for (org, asset) in self.organization_assets() {
+            let epoch = self.blockchain.epoch();
+            let (Some(published), Some(address_set)) = (
+                self.blockchain
+                    .delta_commitment(epoch, &org.identifier(), asset),
+                self.blockchain
+                    .address_set_commitment(epoch, &org.identifier()),
+            ) else {
+                unpublished.push(org.identifier());
+                continue;
+            };
+            identifiers.push(org.identifier());
+            proofs.push((
+                org.generate_epoch_proof(asset, *address_set.salt(), &proving_key),
+                epoch_public_inputs(&published, address_set.root()),
+            ));
+        }
+        let mut failed: Vec<String> = batch_verify_or_find_invalid(&verifying_key, &proofs)
+            .into_iter()
+            .map(|index| identifiers[index].clone())
+            .chain(unpublished)
+            .collect();
+        failed.sort_unstable();
+        failed.dedup();
+        failed
+    }
+    /// Every organization's commitment to its addresses, salted with the latest block hash, ordered by identifier
+    pub fn address_set_commitments(&self) -> Vec<(String, AddressSetCommitment)> {
+        let salt = self.address_set_salt();
+        let mut commitments: Vec<_> = self
+            .organizations
+            .values()
+            .map(|org| (org.identifier(), org.address_set_commitment(salt)))
+            .collect();
+        commitments.sort_unstable_by(|(first, _), (second, _)| first.cmp(second));
+        commitments
+    }
+    /// Check that no two organizations claim the same address, comparing only their address set commitments.
+    ///
+    /// Returns the identifiers of the organizations whose commitment is malformed, was made under another salt or shares
+    /// an address with another organization (an empty vector means the address sets are disjoint).
+    pub fn verify_address_disjointness(&self) -> Vec<String> {
+        failed_disjointness(&self.address_set_commitments())
+    }
+    /// Have every organization post its artifacts for the current epoch on the bulletin board: its delta commitments,
+    /// its address set commitment, the root of its serial numbers and its proofs out of `submissions`, signed with the
+    /// address it registered when it joined. The network posts the epoch's external flows ahead of them, and every
+    /// posting signs their hash. Has to happen before the deltas are cleared at the end of the epoch.
+    ///
+    /// Either everything is posted or nothing is: the postings go onto a copy of the board first, which only replaces
+    /// the board once every one of them went through.
+    pub fn post_epoch_artifacts(
+        &mut self,
+        submissions: &[ProofSubmission],
+    ) -> Result<(), BulletinBoardError> {
+        let mut orgs: Vec<&Organization<F>> = self.organizations.values().collect();
+        orgs.sort_unstable_by_key(|org| org.identifier());
+        if let Some(org) = orgs.iter().find(|org| org.known_addresses().is_empty()) {
+            return Err(BulletinBoardError::NoSigningAddress(org.identifier()));
+        }
+        let epoch = self.blockchain.epoch();
+        let salt = self.address_set_salt();
+        let flows: HashMap<AssetId, ExternalFlow> = self
+            .external_flows
+            .iter()
+            .map(|(asset, flow)| (*asset, flow.clone()))
+            .collect();
+        let flows_hash = external_flows_hash(&flows);
+        let mut board = self.bulletin_board.clone();
+        board.post_external_flows(epoch, flows)?;
+        for org in orgs {
+            let identifier = org.identifier();
+            let proofs = submissions
+                .iter()
+                .filter(|submission| submission.identifier() == identifier)
+                .cloned()
+                .collect();
+            let posting = Posting::new(
+                identifier,
+                epoch,
+                org.assets()
+                    .into_iter()
+                    .map(|asset| (asset, org.delta_commitment(asset)))
+                    .collect(),
+                org.address_set_commitment(salt),
+                serial_number_root(&Vec::from(org.serial_numbers())),
+                proofs,
+                flows_hash,
+                board.tip_hash(),
+                &org.known_addresses()[0],
+            )?;
+            board.post(posting, self.blockchain.membership_history())?;
+        }
+        self.bulletin_board = board;
+        Ok(())
+    }
+    pub fn bulletin_board(&self) -> &BulletinBoard<F> {
+        &self.bulletin_board
+    }
+    /// Carry on with a bulletin board published earlier, postings of later epochs go on top of it
+    pub fn set_bulletin_board(&mut self, board: BulletinBoard<F>) {
+        self.bulletin_board = board;
+    }
+    pub fn validate_no_zombie_serial_numbers(&mut self) {
+        let blockchain = self.blockchain.inner();
 
         // Iterate over each organization and check for zombie serial numbers
         for org in self.organizations.values_mut() {
-            let has_zombie_sn = org.unused_serial_numbers().iter().any(|sn| {
-                blockchain_keys
-                    .iter()
-                    .any(|key| sn.is_eq(key).unwrap().value().unwrap())
-            });
+            let has_zombie_sn = org
+                .unused_serial_numbers()
+                .iter()
+                .any(|sn| blockchain.contains_key(sn));
 
             if has_zombie_sn {
                 panic!("Zombie SN used!");
@@ -150,8 +1012,677 @@ where
         self.blockchain.clone()
     }
 }
+/// A setup for the epoch circuit shared by every organization. The circuit has the same shape regardless of the
+/// balances, so one set of keys can be used to prove (and batch verify or aggregate) every organization's epoch.
+pub fn shared_epoch_setup() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+        EpochBalanceCircuit::blank(),
+        &mut OsRng,
+    )
+    .unwrap()
+}
+/// A setup for the output range proofs of every transaction on the network
+pub fn shared_range_setup() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+        ValueRangeCircuit::blank(),
+        &mut OsRng,
+    )
+    .unwrap()
+}
+/// A setup for the `JoinSplit` proofs of every transaction on the network. The circuit has a fixed number of input and
+/// output slots, so one set of keys proves every transaction spending notes
+pub fn shared_join_split_setup() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+        JoinSplitCircuit::blank(),
+        &mut OsRng,
+    )
+    .unwrap()
+}
+/// A setup for the asset circuit shared by every organization.
+pub fn shared_asset_setup() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+        AssetProof::new(0, vec![], vec![], vec![]),
+        &mut OsRng,
+    )
+    .unwrap()
+}
+/// A setup for the blockchain validator circuit shared by every organization
+pub fn shared_validator_setup() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+        BlockchainValidatorCircuit::new(vec![], vec![], vec![], vec![]),
+        &mut OsRng,
+    )
+    .unwrap()
+}
+#[allow(dead_code)]
 fn pause_until_enter() {
     println!("Press Enter to continue...");
     let stdin = std::io::stdin();
     let _ = stdin.lock().lines().next(); // Wait for user to press Enter
 }
+/// Setups the tests of several modules start from
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use crate::core::key_derivation::MasterSeed;
+    use std::sync::OnceLock;
+
+    /// A network, the range proving key to build transactions for it with, and the addresses of org1 and org2
+    pub(crate) type TwoOrganizations = (
+        Network<Fr>,
+        ProvingKey<Bn254>,
+        Vec<Address<Fr>>,
+        Vec<Address<Fr>>,
+    );
+
+    /// The `JoinSplit` setup takes far longer than any other, so the tests share a single one
+    pub(crate) fn join_split_keys() -> (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+        static KEYS: OnceLock<(ProvingKey<Bn254>, VerifyingKey<Bn254>)> = OnceLock::new();
+        KEYS.get_or_init(shared_join_split_setup).clone()
+    }
+    /// A network of org1 and org2, each with a balance of 100, `address_count` addresses derived from the seeds
+    /// `[1; 32]` and `[2; 32]` and the serial numbers of those addresses
+    pub(crate) fn two_organization_network(address_count: usize) -> TwoOrganizations {
+        let mut network = Network::<Fr>::with_proof_keys(shared_range_setup(), join_split_keys());
+        let range_key = network.range_proving_key().clone();
+        let first_addresses =
+            Organization::create_known_addresses(&MasterSeed::new([1u8; 32]), address_count);
+        let second_addresses =
+            Organization::create_known_addresses(&MasterSeed::new([2u8; 32]), address_count);
+        for (identifier, addresses) in [("org1", &first_addresses), ("org2", &second_addresses)] {
+            network.add_organization(Organization::new(
+                identifier.to_owned(),
+                100,
+                addresses.clone(),
+                Organization::create_unused_serial_numbers_list(addresses),
+            ));
+        }
+        (network, range_key, first_addresses, second_addresses)
+    }
+    /// Forward a transaction the way its sender does: hand out the openings, then forward the shared transaction
+    pub(crate) fn forward(network: &mut Network<Fr>, t: Transaction<Fr>) {
+        network.send_openings(&t);
+        network.forward_transaction(t.shared().clone());
+    }
+    /// Submit a transaction the way its sender does: hand out the openings, then submit the shared transaction
+    pub(crate) fn submit(
+        network: &mut Network<Fr>,
+        t: Transaction<Fr>,
+    ) -> Result<(), MempoolError> {
+        network.send_openings(&t);
+        network.submit_transaction(t.shared().clone())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{
+        fixtures::{forward, submit, two_organization_network},
+        *,
+    };
+    use crate::core::{address::Address, key_derivation::MasterSeed, pedersen::ValueCommitment};
+
+    #[test]
+    fn test_address_index() {
+        let (network, _, first_addresses, second_addresses) = two_organization_network(3);
+        for (addresses, identifier) in [(first_addresses, "org1"), (second_addresses, "org2")] {
+            for address in addresses {
+                let org = network.organization_of(address.public_key()).unwrap();
+                assert_eq!(org.identifier(), identifier);
+            }
+        }
+        assert!(network.organization_of(&Fr::from(12345u64)).is_none());
+        assert!(network.verify_address_disjointness().is_empty());
+    }
+
+    #[test]
+    fn test_zero_sum_delta_commitments() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(1u64),
+                30,
+                first_addresses[0].clone(),
+                second_addresses[0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                12,
+                second_addresses[0].clone(),
+                first_addresses[0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        network.transfer_delta_to_organization_balance();
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+        assert!(network.verify_published_delta_commitments().is_empty());
+
+        // An organization publishing a commitment to a different delta breaks both checks
+        network.blockchain.publish_delta_commitment(
+            "org1".to_owned(),
+            AssetId::NATIVE,
+            ValueCommitment::new(-10, &crate::core::pedersen::random_blinding()),
+        );
+        assert!(!network.verify_zero_sum());
+        assert_eq!(network.verify_published_delta_commitments(), vec!["org1"]);
+    }
+
+    #[test]
+    fn test_spending_notes() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let note = network.mint_note(*first_addresses[0].public_key(), AssetId::NATIVE, 100);
+
+        let transaction = Transaction::spend_note(
+            Fr::from(1u64),
+            &note,
+            30,
+            0,
+            first_addresses[0].clone(),
+            second_addresses[0].public_address(),
+            &range_key,
+            &join_split_key,
+        );
+        let serial_number = transaction.serial_number();
+        forward(&mut network, transaction);
+
+        let organizations = network.organizations();
+        // The sender keeps the change, the receiver holds the new note and the spent note is gone
+        assert_eq!(organizations["org1"].note_balance(AssetId::NATIVE), 70);
+        assert_eq!(organizations["org2"].note_balance(AssetId::NATIVE), 30);
+        assert!(!organizations["org1"].notes().contains(&note));
+        assert_eq!(network.blockchain().note_commitments().len(), 3);
+        // The network only ever sees the nullifier the sender proved, never the key it comes from
+        assert!(network.blockchain().is_spent(&serial_number));
+    }
+
+    #[test]
+    fn test_join_split_across_organizations() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let third_addresses = Organization::create_known_addresses(&MasterSeed::new([3u8; 32]), 1);
+        network.add_organization(Organization::new(
+            "org3".to_owned(),
+            100,
+            third_addresses.clone(),
+            vec![],
+        ));
+        let sender = first_addresses[0].clone();
+        let inputs = vec![
+            network.mint_note(*sender.public_key(), AssetId::NATIVE, 40),
+            network.mint_note(*sender.public_key(), AssetId::NATIVE, 25),
+        ];
+
+        let transaction = Transaction::join_split(
+            Fr::from(1u64),
+            sender.clone(),
+            inputs,
+            vec![
+                (second_addresses[0].public_address(), 30),
+                (third_addresses[0].public_address(), 20),
+                (sender.public_address(), 15),
+            ],
+            0,
+            &range_key,
+            &join_split_key,
+        );
+        let (root, value_commitment) = (transaction.root(), transaction.value_commitment());
+        let serial_numbers = transaction.serial_numbers();
+        forward(&mut network, transaction);
+
+        let organizations = network.organizations();
+        // Both inputs are spent, every output lands with its owner and only the value leaving the sender counts
+        assert_eq!(organizations["org1"].note_balance(AssetId::NATIVE), 15);
+        assert_eq!(organizations["org2"].note_balance(AssetId::NATIVE), 30);
+        assert_eq!(organizations["org3"].note_balance(AssetId::NATIVE), 20);
+        assert_eq!(organizations["org1"].delta(AssetId::NATIVE), -50);
+        for serial_number in &serial_numbers {
+            assert!(network.blockchain().is_spent(serial_number));
+        }
+        // The transaction's value commitment is recorded once, not once per spent note
+        assert_eq!(
+            network.blockchain().value_commitment(&root),
+            Some(value_commitment)
+        );
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+    }
+
+    #[test]
+    fn test_fees_go_to_fee_sink() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        network.add_organization(Organization::new("operator".to_owned(), 0, vec![], vec![]));
+        network.set_fee_sink("operator");
+        let note = network.mint_note(*first_addresses[0].public_key(), AssetId::NATIVE, 100);
+
+        forward(
+            &mut network,
+            Transaction::spend_note(
+                Fr::from(1u64),
+                &note,
+                30,
+                2,
+                first_addresses[0].clone(),
+                second_addresses[0].public_address(),
+                &range_key,
+                &join_split_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                12,
+                second_addresses[0].clone(),
+                first_addresses[0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            )
+            .with_fee(3, &second_addresses[0]),
+        );
+
+        let organizations = network.organizations();
+        // The fee comes out of the sender's change and shows up in the epoch deltas
+        assert_eq!(organizations["org1"].note_balance(AssetId::NATIVE), 80);
+        assert_eq!(organizations["org1"].delta(AssetId::NATIVE), -32 + 12);
+        assert_eq!(organizations["org2"].delta(AssetId::NATIVE), 30 - 15);
+        assert_eq!(organizations["operator"].delta(AssetId::NATIVE), 5);
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+    }
+
+    #[test]
+    fn test_multi_asset_balances() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let asset = AssetId::new(1);
+        for org in network.organizations.values_mut() {
+            org.add_asset(asset, 50);
+        }
+        let note = network.mint_note(*first_addresses[0].public_key(), asset, 50);
+
+        forward(
+            &mut network,
+            Transaction::spend_note(
+                Fr::from(1u64),
+                &note,
+                20,
+                0,
+                first_addresses[0].clone(),
+                second_addresses[0].public_address(),
+                &range_key,
+                &join_split_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                7,
+                second_addresses[0].clone(),
+                first_addresses[0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(3u64),
+                5,
+                second_addresses[0].clone(),
+                first_addresses[0].public_address(),
+                Fr::from(1u64),
+                &range_key,
+            )
+            .with_asset(asset, &second_addresses[0], &range_key),
+        );
+        network.transfer_delta_to_organization_balance();
+
+        let organizations = network.organizations();
+        // Each asset keeps its own books
+        assert_eq!(organizations["org1"].assets(), vec![AssetId::NATIVE, asset]);
+        assert_eq!(organizations["org1"].delta(AssetId::NATIVE), 7);
+        assert_eq!(organizations["org1"].delta(asset), -15);
+        assert_eq!(organizations["org2"].final_balance(asset), 65);
+        assert_eq!(organizations["org1"].note_balance(asset), 35);
+        assert_eq!(organizations["org1"].note_balance(AssetId::NATIVE), 7);
+        assert_eq!(organizations["org1"].serial_numbers_of(asset).len(), 2);
+        assert_eq!(
+            organizations["org1"]
+                .serial_numbers_of(AssetId::NATIVE)
+                .len(),
+            1
+        );
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+
+        // Moving a commitment from one asset to another still sums to zero overall but not per asset
+        let moved = network
+            .blockchain
+            .delta_commitment(0, "org1", asset)
+            .unwrap();
+        network.blockchain.publish_delta_commitment(
+            "org1".to_owned(),
+            AssetId::NATIVE,
+            network
+                .blockchain
+                .delta_commitment(0, "org1", AssetId::NATIVE)
+                .unwrap()
+                + moved,
+        );
+        network.blockchain.publish_delta_commitment(
+            "org1".to_owned(),
+            asset,
+            ValueCommitment::zero(),
+        );
+        assert!(!network.verify_zero_sum());
+    }
+
+    #[test]
+    fn test_intra_organization_transfers() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(2);
+        let join_split_key = network.join_split_proving_key().clone();
+        let (first, second) = (first_addresses[0].clone(), first_addresses[1].clone());
+        network.add_organization(Organization::new("operator".to_owned(), 0, vec![], vec![]));
+        network.set_fee_sink("operator");
+
+        // A self-transfer and a transfer between two addresses of the same organization only use up serial numbers
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(1u64),
+                10,
+                first.clone(),
+                first.public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                15,
+                first.clone(),
+                second.public_address(),
+                Fr::from(1u64),
+                &range_key,
+            ),
+        );
+        let note = network.mint_note(*first.public_key(), AssetId::NATIVE, 40);
+        forward(
+            &mut network,
+            Transaction::spend_note(
+                Fr::from(3u64),
+                &note,
+                25,
+                0,
+                first.clone(),
+                second.public_address(),
+                &range_key,
+                &join_split_key,
+            ),
+        );
+        let org = network.organization("org1").unwrap();
+        assert_eq!(org.delta(AssetId::NATIVE), 0);
+        assert_eq!(
+            org.delta_commitment(AssetId::NATIVE),
+            ValueCommitment::zero()
+        );
+        assert_eq!(org.serial_numbers_of(AssetId::NATIVE).len(), 3);
+        assert_eq!(org.transaction_roots().len(), 3);
+        assert_eq!(org.note_balance(AssetId::NATIVE), 10 + 15 + 40);
+
+        // Only the fee leaves the organization when it pays one on an internal transfer
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(4u64),
+                5,
+                second.clone(),
+                first.public_address(),
+                Fr::from(0u64),
+                &range_key,
+            )
+            .with_fee(2, &second),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(5u64),
+                7,
+                second,
+                second_addresses[0].public_address(),
+                Fr::from(1u64),
+                &range_key,
+            ),
+        );
+        let organizations = network.organizations();
+        assert_eq!(organizations["org1"].delta(AssetId::NATIVE), -9);
+        assert_eq!(
+            organizations["org1"]
+                .serial_numbers_of(AssetId::NATIVE)
+                .len(),
+            5
+        );
+        assert_eq!(organizations["org2"].delta(AssetId::NATIVE), 7);
+        assert_eq!(organizations["operator"].delta(AssetId::NATIVE), 2);
+
+        // The epoch and asset circuits accept the books
+        network.transfer_delta_to_organization_balance();
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+        assert!(network
+            .batch_validate_all_epoch_deltas_and_final_balances()
+            .is_empty());
+        assert!(network.batch_validate_all_assets().is_empty());
+    }
+
+    #[test]
+    fn test_external_wallets() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let join_split_key = network.join_split_proving_key().clone();
+        let wallet = Address::new(Fr::from(7u64));
+        network.register_external_wallet(wallet.public_address());
+        let stranger = Address::new(Fr::from(8u64));
+
+        // A deposit out of the wallet's note and a withdrawal into the wallet
+        let note = network.mint_note(*wallet.public_key(), AssetId::NATIVE, 50);
+        forward(
+            &mut network,
+            Transaction::spend_note(
+                Fr::from(1u64),
+                &note,
+                30,
+                0,
+                wallet.clone(),
+                first_addresses[0].public_address(),
+                &range_key,
+                &join_split_key,
+            ),
+        );
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                12,
+                second_addresses[0].clone(),
+                wallet.public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        let organizations = network.organizations();
+        assert_eq!(organizations["org1"].delta(AssetId::NATIVE), 30);
+        assert_eq!(organizations["org2"].delta(AssetId::NATIVE), -12);
+        let flow = network.external_flow(AssetId::NATIVE);
+        assert_eq!((flow.deposits(), flow.withdrawals()), (30, 12));
+        assert_eq!(flow.net_outflow(), -18);
+        assert!(flow.is_consistent());
+
+        // External wallets spend notes only, unregistered ones can't take part at all
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::new(
+                    Fr::from(3u64),
+                    5,
+                    wallet.clone(),
+                    first_addresses[0].public_address(),
+                    Fr::from(0u64),
+                    &range_key,
+                )
+            ),
+            Err(MempoolError::NoInputNotes)
+        );
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::new(
+                    Fr::from(4u64),
+                    5,
+                    first_addresses[0].clone(),
+                    stranger.public_address(),
+                    Fr::from(1u64),
+                    &range_key,
+                )
+            ),
+            Err(MempoolError::UnknownReceiver)
+        );
+
+        // The organizations' deltas alone don't cancel, together with the published flow they do
+        network.transfer_delta_to_organization_balance();
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+        assert!(network.verify_published_delta_commitments().is_empty());
+        network
+            .blockchain
+            .publish_external_flow(AssetId::NATIVE, ExternalFlow::default());
+        assert!(!network.verify_zero_sum());
+        let mut understated = flow.clone();
+        understated.withdraw(
+            0,
+            ValueCommitment::new(1, &ScalarField::zero()),
+            ScalarField::zero(),
+        );
+        network
+            .blockchain
+            .publish_external_flow(AssetId::NATIVE, understated);
+        assert!(!network.verify_zero_sum());
+    }
+
+    #[test]
+    fn test_membership() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        let addresses = [
+            first_addresses,
+            second_addresses,
+            Organization::create_known_addresses(&MasterSeed::new([3u8; 32]), 1),
+        ];
+        let organization = |identifier: &str, addresses: &Vec<Address<Fr>>| {
+            Organization::new(
+                identifier.to_owned(),
+                100,
+                addresses.clone(),
+                Organization::create_unused_serial_numbers_list(addresses),
+            )
+        };
+        assert_eq!(
+            network.register_organization(organization("org1", &addresses[2])),
+            Err(MembershipError::DuplicateIdentifier("org1".to_owned()))
+        );
+        assert_eq!(
+            network.register_organization(organization("org3", &addresses[0])),
+            Err(MembershipError::DuplicateAddress)
+        );
+
+        // Nobody joins or leaves in the middle of an epoch
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(1u64),
+                30,
+                addresses[0][0].clone(),
+                addresses[1][0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        assert_eq!(
+            network.register_organization(organization("org3", &addresses[2])),
+            Err(MembershipError::NotAtEpochBoundary)
+        );
+        assert_eq!(
+            network.remove_organization("org2").err(),
+            Some(MembershipError::NotAtEpochBoundary)
+        );
+
+        // At the boundary the leaving organization carries out its final balance and its addresses are gone
+        network.transfer_delta_to_organization_balance();
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+        network.clean_deltas_and_balances_at_epoch_end();
+        let org = network.remove_organization("org2").unwrap();
+        assert_eq!(org.available_balance(AssetId::NATIVE), 130);
+        assert_eq!(
+            network
+                .organization("org1")
+                .unwrap()
+                .initial_balance(AssetId::NATIVE),
+            70
+        );
+        network
+            .register_organization(organization("org3", &addresses[2]))
+            .unwrap();
+        assert_eq!(
+            submit(
+                &mut network,
+                Transaction::new(
+                    Fr::from(2u64),
+                    10,
+                    addresses[0][0].clone(),
+                    addresses[1][0].public_address(),
+                    Fr::from(1u64),
+                    &range_key,
+                )
+            ),
+            Err(MempoolError::UnknownReceiver)
+        );
+
+        // The leaving organization's commitments only count towards the epochs it took part in
+        network.publish_delta_commitments();
+        assert!(network.verify_zero_sum());
+        let blockchain = network.blockchain();
+        assert!(EpochStatement::from_blockchain(&blockchain, 0).is_zero_sum());
+        assert_eq!(blockchain.delta_commitments(0).len(), 2);
+        assert_eq!(
+            blockchain.delta_commitment(1, "org2", AssetId::NATIVE),
+            None
+        );
+        let history = blockchain.membership_history();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[2].identifier(), "org2");
+        assert_eq!(history[2].change(), MembershipChange::Left);
+        assert_eq!((history[2].epoch(), history[2].height()), (1, 1));
+        assert_eq!(history[2].balances()[&AssetId::NATIVE], 130);
+        assert_eq!(blockchain.members(0), vec!["org1", "org2"]);
+        assert_eq!(blockchain.members(1), vec!["org1", "org3"]);
+    }
+}
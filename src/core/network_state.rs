@@ -0,0 +1,367 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use ark_bn254::Bn254;
+use ark_ff::PrimeField;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use rand::{CryptoRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    key_derivation::MasterSeed,
+    network::{
+        shared_asset_setup, shared_epoch_setup, shared_join_split_setup, shared_range_setup,
+        shared_validator_setup, Network,
+    },
+    org::Organization,
+    transaction::Transaction,
+    transaction_builder::TransactionBuilder,
+    wire::hex,
+};
+use crate::verifier::Auditor;
+
+/// Identifier of the organization collecting fees when the network charges them
+pub const OPERATOR: &str = "operator";
+
+/// How to recreate an organization: its addresses are derived from the seed, so the seed is a secret
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrganizationSpec {
+    identifier: String,
+    #[serde(with = "hex")]
+    seed: [u8; 32],
+    address_count: usize,
+    initial_balance: i32,
+}
+impl OrganizationSpec {
+    pub fn new(
+        identifier: String,
+        seed: [u8; 32],
+        address_count: usize,
+        initial_balance: i32,
+    ) -> Self {
+        Self {
+            identifier,
+            seed,
+            address_count,
+            initial_balance,
+        }
+    }
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn organization<F: PrimeField>(&self) -> Organization<F> {
+        let addresses =
+            Organization::create_known_addresses(&MasterSeed::new(self.seed), self.address_count);
+        let serial_numbers = Organization::create_unused_serial_numbers_list(&addresses);
+        Organization::new(
+            self.identifier.clone(),
+            self.initial_balance,
+            addresses,
+            serial_numbers,
+        )
+    }
+}
+
+/// A network persisted between runs: the organizations it was created with, the setups of its transaction proofs and
+/// of the circuits organizations prove their epochs with, the transactions of every block, in the order they were
+/// produced, and where each finished epoch ended. Loading replays the blocks and epochs, which recreates the
+/// same blockchain, balances and delta commitments since the senders' transactions carry the openings they hand to
+/// the receivers along with their proofs and signatures.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct NetworkState<F: PrimeField> {
+    organizations: Vec<OrganizationSpec>,
+    transaction_fee: u64, // charged on every simulated transaction and credited to the operator
+    block_size: usize,    // maximum number of transactions per block, 0 for a block per transaction
+    #[serde(with = "hex")]
+    range_proving_key: ProvingKey<Bn254>,
+    #[serde(with = "hex")]
+    range_verifying_key: VerifyingKey<Bn254>,
+    #[serde(with = "hex")]
+    join_split_proving_key: ProvingKey<Bn254>,
+    #[serde(with = "hex")]
+    join_split_verifying_key: VerifyingKey<Bn254>,
+    #[serde(with = "hex")]
+    epoch_proving_key: ProvingKey<Bn254>,
+    #[serde(with = "hex")]
+    asset_proving_key: ProvingKey<Bn254>,
+    #[serde(with = "hex")]
+    validator_proving_key: ProvingKey<Bn254>,
+    #[serde(with = "hex")]
+    auditor: Auditor, // verifying keys of the epoch and asset circuits
+    #[serde(with = "hex")]
+    validator_verifying_key: VerifyingKey<Bn254>,
+    blocks: Vec<Vec<Transaction<F>>>,
+    #[serde(default)]
+    epoch_ends: Vec<usize>, // number of blocks at the end of each finished epoch
+}
+impl<F> NetworkState<F>
+where
+    F: PrimeField,
+{
+    /// A network of `org_count` organizations with random seeds, and an operator collecting the fees if there are any
+    pub fn new<R: RngCore + CryptoRng>(
+        org_count: usize,
+        addresses_per_organization: usize,
+        initial_balance: i32,
+        transaction_fee: u64,
+        block_size: usize,
+        rng: &mut R,
+    ) -> Self {
+        let mut organizations: Vec<OrganizationSpec> = (0..org_count)
+            .map(|i| {
+                OrganizationSpec::new(
+                    format!("org{}", i + 1),
+                    *MasterSeed::random(rng).as_bytes(),
+                    addresses_per_organization,
+                    initial_balance,
+                )
+            })
+            .collect();
+        if transaction_fee > 0 {
+            // The operator only holds an address to sign its postings with, it never sends or receives transfers
+            organizations.push(OrganizationSpec::new(
+                OPERATOR.to_owned(),
+                *MasterSeed::random(rng).as_bytes(),
+                1,
+                0,
+            ));
+        }
+        let (range_proving_key, range_verifying_key) = shared_range_setup();
+        let (join_split_proving_key, join_split_verifying_key) = shared_join_split_setup();
+        let (epoch_proving_key, epoch_verifying_key) = shared_epoch_setup();
+        let (asset_proving_key, asset_verifying_key) = shared_asset_setup();
+        let (validator_proving_key, validator_verifying_key) = shared_validator_setup();
+        Self {
+            organizations,
+            transaction_fee,
+            block_size,
+            range_proving_key,
+            range_verifying_key,
+            join_split_proving_key,
+            join_split_verifying_key,
+            epoch_proving_key,
+            asset_proving_key,
+            validator_proving_key,
+            auditor: Auditor::new(epoch_verifying_key, asset_verifying_key),
+            validator_verifying_key,
+            blocks: Vec::new(),
+            epoch_ends: Vec::new(),
+        }
+    }
+    pub fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, json)
+    }
+    pub fn organizations(&self) -> &[OrganizationSpec] {
+        &self.organizations
+    }
+    pub fn transaction_fee(&self) -> u64 {
+        self.transaction_fee
+    }
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+    pub fn blocks(&self) -> &[Vec<Transaction<F>>] {
+        &self.blocks
+    }
+    /// Number of the current epoch, counting from zero
+    pub fn epoch(&self) -> usize {
+        self.epoch_ends.len()
+    }
+    /// Close the current epoch, the blocks recorded so far belong to it
+    pub fn end_epoch(&mut self) {
+        self.epoch_ends.push(self.blocks.len());
+    }
+    pub fn epoch_proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.epoch_proving_key
+    }
+    pub fn asset_proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.asset_proving_key
+    }
+    pub fn validator_proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.validator_proving_key
+    }
+    /// The auditor for the epoch and asset proofs, holding the verifying keys of the setup made with the state
+    pub fn auditor(&self) -> &Auditor {
+        &self.auditor
+    }
+    pub fn validator_verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.validator_verifying_key
+    }
+    /// Recreate the network by adding the organizations and replaying every block, ending the epochs where they ended
+    pub fn network(&self) -> Network<F> {
+        let mut network = Network::with_proof_keys(
+            (
+                self.range_proving_key.clone(),
+                self.range_verifying_key.clone(),
+            ),
+            (
+                self.join_split_proving_key.clone(),
+                self.join_split_verifying_key.clone(),
+            ),
+        );
+        for spec in &self.organizations {
+            network.add_organization(spec.organization());
+        }
+        if self.transaction_fee > 0 {
+            network.set_fee_sink(OPERATOR);
+        }
+        network.set_block_size(self.block_size.max(1));
+        let mut epoch_ends = self.epoch_ends.iter().peekable();
+        for (height, transactions) in self.blocks.iter().enumerate() {
+            while epoch_ends.next_if_eq(&&height).is_some() {
+                network.clean_deltas_and_balances_at_epoch_end();
+            }
+            for t in transactions {
+                network.send_openings(t);
+                if let Err(error) = network.submit_transaction(t.shared().clone()) {
+                    panic!("Transaction of block {height} no longer applies: {error}");
+                }
+            }
+            if !network.produce_block().1.is_empty() {
+                panic!("Block {height} couldn't be replayed");
+            }
+        }
+        for _ in epoch_ends {
+            network.clean_deltas_and_balances_at_epoch_end();
+        }
+        network
+    }
+    /// Generate up to `transaction_count` random transactions between organizations holding addresses, other than the
+    /// operator, and record the blocks they end up in. Transactions the sender can't afford are skipped. Returns the
+    /// updated network.
+    pub fn simulate<R: Rng>(&mut self, transaction_count: usize, rng: &mut R) -> Network<F> {
+        let mut network = self.network();
+        let senders: Vec<String> = self
+            .organizations
+            .iter()
+            .filter(|spec| spec.address_count > 0 && spec.identifier != OPERATOR)
+            .map(|spec| spec.identifier.clone())
+            .collect();
+        if senders.is_empty() {
+            return network;
+        }
+        let mut pending: HashMap<F, Transaction<F>> = HashMap::new();
+        for _ in 0..transaction_count {
+            let sender = &senders[rng.gen_range(0..senders.len())];
+            let receiver = &senders[rng.gen_range(0..senders.len())];
+            let Ok(transaction) = TransactionBuilder::new(&network)
+                .from_organization(sender)
+                .to_organization(receiver)
+                .value(rng.gen_range(1..100))
+                .fee(self.transaction_fee)
+                .build()
+            else {
+                continue;
+            };
+            network.send_openings(&transaction);
+            if network
+                .submit_transaction(transaction.shared().clone())
+                .is_err()
+            {
+                continue;
+            }
+            pending.insert(transaction.root(), transaction);
+            if network.mempool().len() >= self.block_size.max(1) {
+                self.record_block(&mut network, &mut pending);
+            }
+        }
+        if !network.mempool().is_empty() {
+            self.record_block(&mut network, &mut pending);
+        }
+        network
+    }
+    /// Produce a block and record its transactions in the order they were applied
+    fn record_block(&mut self, network: &mut Network<F>, pending: &mut HashMap<F, Transaction<F>>) {
+        let (block, _) = network.produce_block();
+        self.blocks.push(
+            block
+                .transaction_roots()
+                .iter()
+                .map(|root| pending.remove(root).unwrap())
+                .collect(),
+        );
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::bulletin_board::BulletinBoard,
+        verifier::{EpochStatement, Verdict},
+    };
+    use ark_bn254::Fr;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_network_state_replay() {
+        let mut state = NetworkState::<Fr>::new(3, 2, 1000, 1, 4, &mut OsRng);
+        let network = state.simulate(10, &mut OsRng);
+        assert_eq!(state.organizations().len(), 4);
+        assert_eq!(network.blockchain().height(), state.blocks().len() as u64);
+        assert!(state.blocks().iter().all(|block| block.len() <= 4));
+
+        // The state survives a round trip and replays to the same blockchain and commitments
+        let loaded: NetworkState<Fr> =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        let mut replayed = loaded.network();
+        let mut network = network;
+        assert_eq!(
+            replayed.blockchain().latest_block_hash(),
+            network.blockchain().latest_block_hash()
+        );
+        network.publish_delta_commitments();
+        replayed.publish_delta_commitments();
+        assert_eq!(
+            replayed.blockchain().delta_commitments(0),
+            network.blockchain().delta_commitments(0)
+        );
+        assert!(replayed.verify_zero_sum());
+
+        // Proofs made with the persisted proving keys pass an audit with the persisted verifying keys, reading only the
+        // published bulletin board
+        let identifiers: Vec<String> = loaded
+            .organizations()
+            .iter()
+            .map(|spec| spec.identifier().to_owned())
+            .collect();
+        replayed.transfer_delta_to_organization_balance();
+        let submissions = replayed.prove_epochs(
+            &identifiers,
+            loaded.epoch_proving_key(),
+            loaded.asset_proving_key(),
+        );
+        replayed.post_epoch_artifacts(&submissions).unwrap();
+        let board: BulletinBoard<Fr> =
+            serde_json::from_str(&serde_json::to_string(replayed.bulletin_board()).unwrap())
+                .unwrap();
+        let membership = replayed.blockchain().membership_history().to_vec();
+        assert!(board.verify_chain(&membership));
+        let statement = EpochStatement::from_bulletin_board(&board, &membership, 0);
+        assert!(statement.is_zero_sum());
+        let verdicts = loaded.auditor().audit(&statement, &board.submissions(0));
+        assert_eq!(verdicts.len(), 4);
+        assert!(verdicts.iter().all(Verdict::is_valid));
+
+        // The next epoch's blocks replay on top of the balances the last epoch ended with
+        state.end_epoch();
+        let network = state.simulate(4, &mut OsRng);
+        let replayed = state.network();
+        assert_eq!(replayed.blockchain().epoch(), 1);
+        assert_eq!(
+            replayed.blockchain().latest_block_hash(),
+            network.blockchain().latest_block_hash()
+        );
+        for org in network.organizations().values() {
+            assert_eq!(
+                replayed.organizations()[&org.identifier()].available_balances(),
+                org.available_balances()
+            );
+        }
+    }
+}
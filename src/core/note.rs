@@ -0,0 +1,127 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{asset::AssetId, serial_number::TransactionSerialNumber, wire::hex};
+use crate::utils::sha256_bytes_to_field;
+
+/// Prefix of the commitment input, keeps commitments apart from nullifiers and addresses
+pub const NOTE_COMMITMENT_TAG: u8 = 0b10;
+
+/// A coin in the Zcash sense: `value` units of `asset` owned by the address with public key `owner_public_key`.
+///
+/// `rho` determines the note's serial number once the owner spends it and `randomness` blinds the commitment, so
+/// the commitment on the blockchain says nothing about who owns how much.
+#[derive(
+    Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+#[serde(bound = "")]
+pub struct Note<F: PrimeField> {
+    #[serde(with = "hex")]
+    owner_public_key: F,
+    asset: AssetId,
+    value: u64,
+    #[serde(with = "hex")]
+    rho: F,
+    #[serde(with = "hex")]
+    randomness: F,
+}
+impl<F> Note<F>
+where
+    F: PrimeField,
+{
+    pub fn new(owner_public_key: F, asset: AssetId, value: u64, rho: F, randomness: F) -> Self {
+        Self {
+            owner_public_key,
+            asset,
+            value,
+            rho,
+            randomness,
+        }
+    }
+    /// A fresh note with random ρ and commitment randomness
+    pub fn random<R: Rng>(owner_public_key: F, asset: AssetId, value: u64, rng: &mut R) -> Self {
+        Self::new(owner_public_key, asset, value, F::rand(rng), F::rand(rng))
+    }
+    pub fn owner_public_key(&self) -> &F {
+        &self.owner_public_key
+    }
+    pub fn asset(&self) -> AssetId {
+        self.asset
+    }
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+    pub fn rho(&self) -> &F {
+        &self.rho
+    }
+    pub fn randomness(&self) -> &F {
+        &self.randomness
+    }
+    /// `cm = SHA256(tag || owner_pk || asset || value || ρ || r)`
+    pub fn commitment(&self) -> F {
+        let mut input = vec![NOTE_COMMITMENT_TAG];
+        input.extend(self.owner_public_key.into_bigint().to_bytes_le());
+        input.extend(self.asset.to_le_bytes());
+        input.extend(self.value.to_le_bytes());
+        input.extend(self.rho.into_bigint().to_bytes_le());
+        input.extend(self.randomness.into_bigint().to_bytes_le());
+        sha256_bytes_to_field(input)
+    }
+    /// The serial number revealed when the note is spent. Only the owner's secret key can produce it
+    pub fn nullifier(&self, secret_key: &F) -> F {
+        TransactionSerialNumber::new(secret_key, self.rho).sn()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_commitment_binds_every_field() {
+        let note = Note::new(
+            Fr::from(1u64),
+            AssetId::NATIVE,
+            10,
+            Fr::from(2u64),
+            Fr::from(3u64),
+        );
+        let commitment = note.commitment();
+        assert_eq!(commitment, note.clone().commitment());
+        assert_ne!(
+            commitment,
+            Note::new(
+                Fr::from(1u64),
+                AssetId::NATIVE,
+                11,
+                Fr::from(2u64),
+                Fr::from(3u64)
+            )
+            .commitment()
+        );
+        assert_ne!(
+            commitment,
+            Note::new(
+                Fr::from(1u64),
+                AssetId::NATIVE,
+                10,
+                Fr::from(2u64),
+                Fr::from(4u64)
+            )
+            .commitment()
+        );
+        assert_ne!(
+            commitment,
+            Note::new(
+                Fr::from(1u64),
+                AssetId::new(1),
+                10,
+                Fr::from(2u64),
+                Fr::from(3u64)
+            )
+            .commitment()
+        );
+    }
+}
@@ -1,24 +1,40 @@
-use std::{collections::VecDeque, io::BufRead};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::BufRead,
+};
 
-use super::{address::Address, serial_number::TransactionSerialNumber, transaction::Transaction};
+use super::{
+    address::Address,
+    asset::AssetId,
+    disjointness::AddressSetCommitment,
+    key_derivation::MasterSeed,
+    note::Note,
+    pedersen::ValueCommitment,
+    serial_number::TransactionSerialNumber,
+    transaction::{OutputOpening, SharedTransaction, TransactionOutput},
+};
 use crate::{
     core::fiat_transform::generate_alpha,
     gadgets::{
         asset_proof::{count_occurrences, generate_asset_proof, AssetProof},
         blockchain_validator::{blockchain_validator_generate_proof, BlockchainValidatorCircuit},
-        epoch_circuit::{generate_proof, EpochBalanceCircuit},
+        epoch_circuit::{epoch_public_inputs, generate_proof, EpochBalanceCircuit},
     },
-    utils::{fpvars_to_u64s, prime_fields_to_u64s},
+    utils::prime_fields_to_u64s,
 };
-use ark_bn254::{Bn254, Fr};
+use ark_bn254::Bn254;
 use ark_crypto_primitives::snark::SNARK;
-use ark_ff::PrimeField;
-use ark_groth16::{prepare_verifying_key, r1cs_to_qap::LibsnarkReduction, Groth16};
-use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, R1CSVar};
-use ark_relations::r1cs::ConstraintSystemRef;
+use ark_ed_on_bn254::Fr as ScalarField;
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{
+    prepare_verifying_key, r1cs_to_qap::LibsnarkReduction, Groth16, Proof, ProvingKey,
+};
 use indicatif::ProgressIterator;
 use rand::rngs::OsRng;
 
+/// How many serial numbers each address can produce, one per ρ in `0..SERIAL_NUMBERS_PER_ADDRESS`
+pub const SERIAL_NUMBERS_PER_ADDRESS: u32 = 10;
+
 #[derive(Clone)]
 /// An Organization on a cryptocurrency platform.
 ///
@@ -32,199 +48,379 @@ use rand::rngs::OsRng;
 /// - `known_address_public_keys`: A list of all addresses' public keys belonging to the organization.
 /// - `transaction_root_cache`: A list of the hashed (or "committed") transaction values for transactions the organization participated in.
 /// - `unique_identifier`: A unique name for the Organization.
-/// - `_initial_balance`: An immutable value representing how much "money" the Organization starts the epoch with.
-/// - `final_balance`: The final balance the Organization ends the epoch with.
-/// - `epoch_balance_delta`: The difference between the Organization's initial and final balances for the epoch.
-
+/// - `balances`: The Organization's [`EpochBalance`] in every asset it holds or has traded this epoch.
+/// - `serial_number_assets`: The asset moved by each transaction in `spent_serial_numbers`.
+/// - `owned_notes`: The unspent notes held by the Organization's addresses.
+/// - `output_openings`: The openings of the outputs the Organization sent or received, keyed by note commitment. They
+///   reach it from the sender, outside the network.
 pub struct Organization<F: PrimeField> {
-    // TODO: Change the balance counters to u32 and keep a flag for each of those whether or not they are negative
-    spent_serial_numbers: VecDeque<FpVar<F>>,
+    spent_serial_numbers: VecDeque<F>,
     known_address_public_keys: Vec<Address<F>>,
-    transaction_root_cache: VecDeque<FpVar<F>>,
+    transaction_root_cache: VecDeque<F>,
     unique_identifier: String,
-    _initial_balance: i32,
+    balances: BTreeMap<AssetId, EpochBalance>,
+    serial_number_assets: HashMap<F, AssetId>,
+    unused_serial_numbers: Vec<F>,
+    owned_notes: Vec<Note<F>>,
+    output_openings: HashMap<F, OutputOpening<F>>,
+}
+/// An Organization's balance in a single asset over an epoch.
+///
+/// # Fields
+///
+/// - `initial_balance`: An immutable value representing how much of the asset the Organization starts the epoch with.
+/// - `final_balance`: The final balance the Organization ends the epoch with.
+/// - `epoch_balance_delta`: The difference between the Organization's initial and final balances for the epoch.
+/// - `delta_commitment`: The sum of the value commitments of the epoch's transactions, received ones added and sent ones
+///   subtracted. It commits to `epoch_balance_delta` under `delta_blinding`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EpochBalance {
+    // TODO: Change the balance counters to u32 and keep a flag for each of those whether or not they are negative
+    initial_balance: i32,
     final_balance: i32,
     epoch_balance_delta: i32,
-    unused_serial_numbers: Vec<FpVar<F>>,
+    delta_commitment: ValueCommitment,
+    delta_blinding: ScalarField,
+}
+impl EpochBalance {
+    pub fn new(initial_balance: i32) -> Self {
+        Self {
+            initial_balance,
+            final_balance: initial_balance,
+            ..Self::default()
+        }
+    }
+    pub fn initial_balance(&self) -> i32 {
+        self.initial_balance
+    }
+    pub fn final_balance(&self) -> i32 {
+        self.final_balance
+    }
+    pub fn delta(&self) -> i32 {
+        self.epoch_balance_delta
+    }
+    pub fn delta_commitment(&self) -> ValueCommitment {
+        self.delta_commitment
+    }
+    /// Generate the epoch balance proof against a proving key shared by every organization and asset, bound to the
+    /// root of the organization's address set commitment.
+    pub fn generate_epoch_proof(
+        &self,
+        address_set_root: [u8; 32],
+        proving_key: &ProvingKey<Bn254>,
+    ) -> Proof<Bn254> {
+        generate_proof(
+            self.initial_balance,
+            self.epoch_balance_delta,
+            self.final_balance,
+            self.delta_commitment,
+            self.delta_blinding,
+            address_set_root,
+            proving_key,
+        )
+    }
 }
 impl<F> Organization<F>
 where
     F: PrimeField,
 {
+    /// `initial_balance` is the organization's balance in the native asset, see [`Self::add_asset`] for others
     pub fn new(
         unique_identifier: String,
         initial_balance: i32,
         known_addresses: Vec<Address<F>>,
-        unused_serial_numbers: Vec<FpVar<F>>,
+        unused_serial_numbers: Vec<F>,
     ) -> Self {
         Self {
             spent_serial_numbers: VecDeque::new(),
             known_address_public_keys: known_addresses,
             unique_identifier,
             transaction_root_cache: VecDeque::new(),
-            // NOTE: this field should not be directly accessed or mutated. There is a getter provided to retrieve the value
-            _initial_balance: initial_balance,
-            final_balance: initial_balance,
-            epoch_balance_delta: 0,
+            balances: BTreeMap::from([(AssetId::NATIVE, EpochBalance::new(initial_balance))]),
+            serial_number_assets: HashMap::new(),
             unused_serial_numbers,
+            owned_notes: Vec::new(),
+            output_openings: HashMap::new(),
         }
     }
-    pub fn add_serial_number(&mut self, sn: FpVar<F>) {
+    /// Start the epoch holding `initial_balance` of `asset`. `initial_balance` in [`Self::new`] is the native asset's.
+    pub fn add_asset(&mut self, asset: AssetId, initial_balance: i32) {
+        self.balances
+            .insert(asset, EpochBalance::new(initial_balance));
+    }
+    /// Every asset the organization holds or has traded this epoch
+    pub fn assets(&self) -> Vec<AssetId> {
+        self.balances.keys().copied().collect()
+    }
+    /// The organization's balance in `asset`, all zeros for assets it never held
+    pub fn balance(&self, asset: AssetId) -> EpochBalance {
+        self.balances.get(&asset).copied().unwrap_or_default()
+    }
+    /// Record the serial number of a transaction the organization took part in. Serial numbers are keyed by the
+    /// spender's secret key, so only the sending side will find it in its own pool of unused serial numbers
+    pub fn add_serial_number(&mut self, sn: F, asset: AssetId) {
+        // The network adds a transaction's serial numbers to each organization taking part once, even when value
+        // moves between the organization's own addresses
         if self.has_serial_number(&sn) {
-            // TODO: This is to purely paper over some issue with repeat sn's being added I think its because our random range is not a full u32 spectrum anymore
-            //        but it is unclear
-            return;
             panic!("Repeat sn added to spent_serial_numbers");
         }
-        self.spent_serial_numbers.push_back(sn.clone());
-        let index_to_remove = self
-            .unused_serial_numbers
+        self.spent_serial_numbers.push_back(sn);
+        self.serial_number_assets.insert(sn, asset);
+        if let Some(index_to_remove) = self.unused_serial_numbers.iter().position(|x| *x == sn) {
+            let _ = self.unused_serial_numbers.remove(index_to_remove);
+        }
+    }
+    /// Record a serial number the organization spent. It has to come out of the organization's own pool
+    pub fn spend_serial_number(&mut self, sn: F, asset: AssetId) {
+        if !self.has_serial_number(&sn) && !self.unused_serial_numbers.contains(&sn) {
+            panic!("Couldn't find serial number");
+        }
+        self.add_serial_number(sn, asset);
+    }
+    /// Keep the opening of an output the organization sends or receives
+    pub fn receive_opening(&mut self, opening: OutputOpening<F>) {
+        self.output_openings
+            .insert(opening.note().commitment(), opening);
+    }
+    /// The opening of `output` the organization holds, `None` unless it was handed one that opens the output
+    pub fn opening(&self, output: &TransactionOutput<F>) -> Option<&OutputOpening<F>> {
+        self.output_openings
+            .get(&output.note_commitment())
+            .filter(|opening| opening.opens(output))
+    }
+    pub fn receive_note(&mut self, note: Note<F>) {
+        self.owned_notes.push(note);
+    }
+    /// Drop the note that `sn` spends, if one of the organization's addresses owns it
+    pub fn consume_note(&mut self, sn: &F) -> Option<Note<F>> {
+        let index = self.owned_notes.iter().position(|note| {
+            self.address(note.owner_public_key())
+                .is_some_and(|owner| note.nullifier(owner.secret_key()) == *sn)
+        })?;
+        Some(self.owned_notes.remove(index))
+    }
+    pub fn notes(&self) -> Vec<Note<F>> {
+        self.owned_notes.clone()
+    }
+    /// Total value of `asset` held in the organization's unspent notes
+    pub fn note_balance(&self, asset: AssetId) -> u64 {
+        self.owned_notes
             .iter()
-            .position(|x| x.is_eq(&sn).unwrap().value().unwrap())
-            .expect("Couldn't find serial number");
-        let _ = self.unused_serial_numbers.remove(index_to_remove);
+            .filter(|note| note.asset() == asset)
+            .map(Note::value)
+            .sum()
     }
     pub fn known_addresses(&self) -> Vec<Address<F>> {
         self.known_address_public_keys.clone()
     }
-    pub fn serial_numbers(&self) -> VecDeque<FpVar<F>> {
+    /// Commit to the organization's addresses under `salt` so they can be checked against other organizations'
+    pub fn address_set_commitment(&self, salt: [u8; 32]) -> AddressSetCommitment {
+        let public_keys: Vec<F> = self
+            .known_address_public_keys
+            .iter()
+            .map(|address| *address.public_key())
+            .collect();
+        AddressSetCommitment::new(salt, &public_keys)
+    }
+    pub fn serial_numbers(&self) -> VecDeque<F> {
         self.spent_serial_numbers.clone()
     }
-    pub fn add_root(&mut self, root: FpVar<F>) {
+    /// The serial numbers of the transactions moving `asset` the organization took part in
+    pub fn serial_numbers_of(&self, asset: AssetId) -> Vec<F> {
+        self.spent_serial_numbers
+            .iter()
+            .filter(|sn| self.serial_number_assets.get(sn) == Some(&asset))
+            .copied()
+            .collect()
+    }
+    /// Roots of the transactions the organization took part in, each once
+    pub fn transaction_roots(&self) -> Vec<F> {
+        self.transaction_root_cache.clone().into()
+    }
+    pub fn add_root(&mut self, root: F) {
         self.transaction_root_cache.push_back(root);
     }
     pub fn clear_delta(&mut self) {
-        self.epoch_balance_delta = 0;
+        for balance in self.balances.values_mut() {
+            balance.epoch_balance_delta = 0;
+            balance.delta_commitment = ValueCommitment::zero();
+            balance.delta_blinding = ScalarField::zero();
+        }
+    }
+    /// Carry every balance over into the next epoch: the epoch's final balance becomes the next initial balance
+    pub fn start_next_epoch(&mut self) {
+        for balance in self.balances.values_mut() {
+            let final_balance = balance.initial_balance + balance.epoch_balance_delta;
+            *balance = EpochBalance::new(final_balance);
+        }
+    }
+    /// Balance of every asset the organization holds right now
+    pub fn available_balances(&self) -> BTreeMap<AssetId, i64> {
+        self.assets()
+            .into_iter()
+            .map(|asset| (asset, self.available_balance(asset)))
+            .collect()
     }
     pub fn clear_final_balance(&mut self) {
-        self.final_balance = 0;
+        for balance in self.balances.values_mut() {
+            balance.final_balance = 0;
+        }
     }
-    pub fn delta(&self) -> i32 {
-        self.epoch_balance_delta
+    pub fn delta(&self, asset: AssetId) -> i32 {
+        self.balance(asset).delta()
     }
-    pub fn final_balance(&self) -> i32 {
-        self.final_balance
+    pub fn final_balance(&self, asset: AssetId) -> i32 {
+        self.balance(asset).final_balance()
     }
-    pub fn initial_balance(&self) -> i32 {
-        self._initial_balance
+    pub fn initial_balance(&self, asset: AssetId) -> i32 {
+        self.balance(asset).initial_balance()
+    }
+    /// What the organization holds of `asset` right now: its initial balance plus this epoch's delta so far
+    pub fn available_balance(&self, asset: AssetId) -> i64 {
+        i64::from(self.initial_balance(asset)) + i64::from(self.delta(asset))
+    }
+    pub fn update_delta(&mut self, asset: AssetId, value: i32) {
+        self.balances.entry(asset).or_default().epoch_balance_delta += value;
+    }
+    /// Fold a transaction's value commitment (negated when the organization is the sender) into the asset's delta
+    /// commitment
+    pub fn update_delta_commitment(
+        &mut self,
+        asset: AssetId,
+        commitment: ValueCommitment,
+        blinding: ScalarField,
+    ) {
+        let balance = self.balances.entry(asset).or_default();
+        balance.delta_commitment = balance.delta_commitment + commitment;
+        balance.delta_blinding += blinding;
     }
-    pub fn update_delta(&mut self, value: i32) {
-        self.epoch_balance_delta += value;
+    pub fn delta_commitment(&self, asset: AssetId) -> ValueCommitment {
+        self.balance(asset).delta_commitment()
     }
-    pub fn update_balance(&mut self, epoch_delta: i32) {
-        self.final_balance += epoch_delta;
+    pub fn update_balance(&mut self, asset: AssetId, epoch_delta: i32) {
+        self.balances.entry(asset).or_default().final_balance += epoch_delta;
     }
     pub fn identifier(&self) -> String {
         self.unique_identifier.clone()
     }
-    pub fn unused_serial_numbers(&self) -> Vec<FpVar<F>> {
+    pub fn unused_serial_numbers(&self) -> Vec<F> {
         self.unused_serial_numbers.clone()
     }
     pub fn dump_info(&self) {
         println!("Organization: {}", self.unique_identifier);
         println!("========================");
-        println!("Initial Epoch Balance: {}", self.initial_balance());
-        println!("Final Epoch Balance: {}", self.final_balance());
-        println!("Epoch Delta: {}", self.epoch_balance_delta);
+        for (asset, balance) in &self.balances {
+            println!("{}:", asset);
+            println!("  Initial Epoch Balance: {}", balance.initial_balance());
+            println!("  Final Epoch Balance: {}", balance.final_balance());
+            println!("  Epoch Delta: {}", balance.delta());
+        }
         // println!("Known Addresses: {:?}", self.known_address_public_keys);
         println!();
     }
-    pub fn has_address(&self, address_public_key: &FpVar<F>) -> bool {
-        self.known_address_public_keys.iter().any(|key| {
-            key.public_key()
-                .is_eq(address_public_key)
-                .unwrap()
-                .value()
-                .unwrap_or(false)
-        })
+    pub fn has_address(&self, address_public_key: &F) -> bool {
+        self.known_address_public_keys
+            .iter()
+            .any(|key| key.public_key() == address_public_key)
     }
-    pub fn has_serial_number(&self, sn: &FpVar<F>) -> bool {
-        self.spent_serial_numbers
+    /// Look up one of the organization's registered addresses by its public key
+    pub fn address(&self, address_public_key: &F) -> Option<&Address<F>> {
+        self.known_address_public_keys
             .iter()
-            .any(|key| key.is_eq(sn).unwrap().value().unwrap())
+            .find(|key| key.public_key() == address_public_key)
+    }
+    pub fn has_serial_number(&self, sn: &F) -> bool {
+        self.spent_serial_numbers.contains(sn)
     }
 
-    pub fn is_involved(&self, t: &Transaction<F>) -> bool {
+    pub fn is_involved(&self, t: &SharedTransaction<F>) -> bool {
         self.has_address(t.sender_address().public_key())
             || self.has_address(t.receiver_address().public_key())
     }
-    pub fn create_known_addresses(
-        cs: &ConstraintSystemRef<F>,
-        num_addresses: usize,
-        offset: usize,
-    ) -> Vec<Address<F>> {
-        let mut addresses = Vec::with_capacity(num_addresses);
-        for i in 0..num_addresses {
-            let input =
-                FpVar::new_input(cs.clone(), || Ok(F::from(i as u64 + offset as u64))).unwrap();
-            // let size = input.to_bytes().unwrap().len();
-            // println!("Address Byte Vector Size: {:?}", size);
-            // pause_until_enter();
-            let address = Address::new(&input);
-            // let address = format!("{}", i + offset); // More descriptive format
-            addresses.push(address);
-        }
-        addresses
-    }
-    pub fn create_unused_serial_numbers_list(cs: &ConstraintSystemRef<F>) -> Vec<FpVar<F>> {
-        let mut unused_sns = Vec::with_capacity(u32::MAX.try_into().unwrap());
-        for i in (0..10u32).progress() {
-            let secret = F::from(i);
-            let secret_element = FpVar::new_input(cs.clone(), || Ok(secret)).unwrap();
-            let unused_sn = TransactionSerialNumber::new(secret_element).sn();
-            unused_sns.push(unused_sn);
+    /// Derive the first `num_addresses` addresses of an organization from its master seed. Calling this again
+    /// with the same seed regenerates the exact same addresses
+    pub fn create_known_addresses(seed: &MasterSeed, num_addresses: usize) -> Vec<Address<F>> {
+        (0..num_addresses as u64)
+            .map(|index| seed.derive_address(index))
+            .collect()
+    }
+    /// The serial numbers each of `addresses` can produce, one per ρ in `0..SERIAL_NUMBERS_PER_ADDRESS`
+    pub fn create_unused_serial_numbers_list(addresses: &[Address<F>]) -> Vec<F> {
+        let mut unused_sns =
+            Vec::with_capacity(SERIAL_NUMBERS_PER_ADDRESS as usize * addresses.len());
+        for address in addresses.iter().progress() {
+            for rho in 0..SERIAL_NUMBERS_PER_ADDRESS {
+                let unused_sn =
+                    TransactionSerialNumber::new(address.secret_key(), F::from(rho)).sn();
+                unused_sns.push(unused_sn);
+            }
         }
         unused_sns
     }
-    pub fn validate_components(&self, blockchain_keys: Vec<F>, blockchain_values: Vec<F>) {
+    /// The ρs whose serial number for `address` is still in the organization's pool of unused serial numbers, in order
+    pub fn unused_rhos(&self, address: &Address<F>) -> Vec<F> {
+        (0..SERIAL_NUMBERS_PER_ADDRESS)
+            .map(F::from)
+            .filter(|rho| {
+                let sn = TransactionSerialNumber::new(address.secret_key(), *rho).sn();
+                self.unused_serial_numbers.contains(&sn)
+            })
+            .collect()
+    }
+    /// Prove and check the organization's epoch and its transactions against fresh setups, with the address set
+    /// committed under `salt`
+    pub fn validate_components(
+        &self,
+        salt: [u8; 32],
+        blockchain_keys: &[F],
+        blockchain_values: &[F],
+    ) {
         let mut rng = OsRng;
         println!(
             "\x1b[32mValidating Organization: {}\x1b[0m",
-            self.identifier()
+            self.unique_identifier
         );
 
+        // Every asset gets its own epoch proof, the circuit has the same shape for all of them
         let (proving_key, verifying_key) =
             Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
-                EpochBalanceCircuit::<Fr>::new(
-                    self.initial_balance(),
-                    self.delta(),
-                    self.final_balance(),
-                ),
+                EpochBalanceCircuit::blank(),
                 &mut rng,
             )
             .unwrap();
-        let proof = generate_proof(
-            self.initial_balance(),
-            self.delta(),
-            self.final_balance(),
-            &proving_key,
-        );
-
         // Prepare the verifying key
         let pvk = prepare_verifying_key(&verifying_key);
+        let address_set_root = *self.address_set_commitment(salt).root();
+        for (asset, balance) in &self.balances {
+            let proof = balance.generate_epoch_proof(address_set_root, &proving_key);
 
-        // Verify the proof
-        let is_valid =
-            Groth16::<Bn254, LibsnarkReduction>::verify_proof(&pvk, &proof, &[]).unwrap();
+            // Verify the proof against the published delta commitment
+            let is_valid = Groth16::<Bn254, LibsnarkReduction>::verify_proof(
+                &pvk,
+                &proof,
+                &epoch_public_inputs(&balance.delta_commitment, &address_set_root),
+            )
+            .unwrap();
 
-        println!("Epoch Proof is valid: {}", is_valid);
+            println!("Epoch Proof for {} is valid: {}", asset, is_valid);
+        }
 
         let (proving_key, verifying_key) =
             Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
                 BlockchainValidatorCircuit::new(
-                    prime_fields_to_u64s(blockchain_keys.clone()),
-                    prime_fields_to_u64s(blockchain_values.clone()),
-                    fpvars_to_u64s(self.transaction_root_cache.clone()),
-                    fpvars_to_u64s(self.spent_serial_numbers.clone()),
+                    prime_fields_to_u64s(blockchain_keys.to_vec()),
+                    prime_fields_to_u64s(blockchain_values.to_vec()),
+                    prime_fields_to_u64s(self.transaction_root_cache.clone().into()),
+                    prime_fields_to_u64s(self.spent_serial_numbers.clone().into()),
                 ),
                 &mut rng,
             )
             .unwrap();
         let proof = blockchain_validator_generate_proof(
-            prime_fields_to_u64s(blockchain_keys.clone()),
-            prime_fields_to_u64s(blockchain_values.clone()),
-            fpvars_to_u64s(self.transaction_root_cache.clone()),
-            fpvars_to_u64s(self.spent_serial_numbers.clone()),
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
+            prime_fields_to_u64s(blockchain_values.to_vec()),
+            prime_fields_to_u64s(self.transaction_root_cache.clone().into()),
+            prime_fields_to_u64s(self.spent_serial_numbers.clone().into()),
             &proving_key,
         );
 
@@ -236,28 +432,26 @@ where
             Groth16::<Bn254, LibsnarkReduction>::verify_proof(&pvk, &proof, &[]).unwrap();
         println!("Blockchain Proof is valid: {}", is_valid);
     }
-
     pub fn validate_assets(
         &self,
-        blockchain_keys: Vec<F>,
+        blockchain_keys: &[F],
         spent_serial_numbers: Vec<u64>,
-        blockchain_values: Vec<F>,
+        blockchain_values: &[F],
     ) {
         let mut rng = OsRng;
         let occurrences: Vec<u32> = count_occurrences(
-            prime_fields_to_u64s(blockchain_keys.clone()),
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
             spent_serial_numbers.clone(),
         )
         .values()
         .copied()
         .collect();
-        println!("occurrences: {:?}", occurrences);
         let (proving_key, verifying_key) =
             Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
                 AssetProof::new(
-                    generate_alpha(blockchain_values.clone()),
+                    generate_alpha(blockchain_values.to_vec()),
                     occurrences.clone(),
-                    prime_fields_to_u64s(blockchain_keys.clone()),
+                    prime_fields_to_u64s(blockchain_keys.to_vec()),
                     spent_serial_numbers.clone(),
                 ),
                 &mut rng,
@@ -265,9 +459,9 @@ where
             .unwrap();
         //? It is unclear to me if using the same alpha value is strictly necessary but I think its better to play it safe and make them the same
         let asset_proof = generate_asset_proof(
-            generate_alpha(blockchain_values),
+            generate_alpha(blockchain_values.to_vec()),
             spent_serial_numbers.clone(),
-            prime_fields_to_u64s(blockchain_keys.clone()),
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
             occurrences,
             &proving_key,
         );
@@ -279,27 +473,75 @@ where
             Groth16::<Bn254, LibsnarkReduction>::verify_proof(&pvk, &asset_proof, &[]).unwrap();
         println!("Asset Proof is valid: {}", is_valid);
     }
+    /// Generate the epoch balance proof for `asset` against a proving key shared by every organization and asset,
+    /// bound to the organization's address set committed under `salt`.
+    pub fn generate_epoch_proof(
+        &self,
+        asset: AssetId,
+        salt: [u8; 32],
+        proving_key: &ProvingKey<Bn254>,
+    ) -> Proof<Bn254> {
+        self.balance(asset)
+            .generate_epoch_proof(*self.address_set_commitment(salt).root(), proving_key)
+    }
+    /// Generate the blockchain validator proof against a proving key shared by every organization.
+    pub fn generate_blockchain_validator_proof(
+        &self,
+        blockchain_keys: &[F],
+        blockchain_values: &[F],
+        proving_key: &ProvingKey<Bn254>,
+    ) -> Proof<Bn254> {
+        blockchain_validator_generate_proof(
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
+            prime_fields_to_u64s(blockchain_values.to_vec()),
+            prime_fields_to_u64s(self.transaction_root_cache.clone().into()),
+            prime_fields_to_u64s(self.spent_serial_numbers.clone().into()),
+            proving_key,
+        )
+    }
+    /// Generate the asset proof for the transactions moving `asset` against a proving key shared by every organization.
+    /// `blockchain_keys` and `blockchain_values` should only hold that asset's transactions.
+    pub fn generate_asset_proof(
+        &self,
+        asset: AssetId,
+        blockchain_keys: &[F],
+        blockchain_values: &[F],
+        proving_key: &ProvingKey<Bn254>,
+    ) -> Proof<Bn254> {
+        let spent_serial_numbers = prime_fields_to_u64s(self.serial_numbers_of(asset));
+        let occurrences: Vec<u32> = count_occurrences(
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
+            spent_serial_numbers.clone(),
+        )
+        .values()
+        .copied()
+        .collect();
+        generate_asset_proof(
+            generate_alpha(blockchain_values.to_vec()),
+            spent_serial_numbers,
+            prime_fields_to_u64s(blockchain_keys.to_vec()),
+            occurrences,
+            proving_key,
+        )
+    }
 }
 pub fn validate_transaction_serial_numbers(
     blockchain_serial_numbers: Vec<u64>,
     spent_serial_numbers: Vec<u64>,
 ) -> bool {
-    spent_serial_numbers.iter().all(|serial| {
-        blockchain_serial_numbers
-            .iter()
-            .any(|blockchain_serial| *serial == *blockchain_serial)
-    })
+    spent_serial_numbers
+        .iter()
+        .all(|serial| blockchain_serial_numbers.contains(serial))
 }
 pub fn validate_transaction_roots(
     blockchain_transaction_roots: Vec<u64>,
     transaction_root_cache: Vec<u64>,
 ) -> bool {
-    transaction_root_cache.iter().all(|t_root| {
-        blockchain_transaction_roots
-            .iter()
-            .any(|blockchain_transaction_root| *t_root == *blockchain_transaction_root)
-    })
+    transaction_root_cache
+        .iter()
+        .all(|t_root| blockchain_transaction_roots.contains(t_root))
 }
+#[allow(dead_code)]
 fn pause_until_enter() {
     println!("Press Enter to continue...");
     let stdin = std::io::stdin();
@@ -0,0 +1,149 @@
+use std::{
+    iter::Sum,
+    ops::{Add, Neg, Sub},
+};
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ed_on_bn254::{EdwardsAffine, Fq, Fr as ScalarField};
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use once_cell::sync::Lazy;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+use super::wire::hex;
+use crate::utils::sha256_bytes_to_field;
+
+/// Domain separator for deriving the blinding generator
+const BLINDING_GENERATOR_DOMAIN: &[u8] = b"ducat/pedersen/blinding-generator";
+
+/// `H`, a generator nobody knows the discrete log of with respect to `G`. It is found by hashing the domain separator
+/// with an increasing counter until the digest is the y coordinate of a curve point, then clearing the cofactor.
+static BLINDING_GENERATOR: Lazy<EdwardsAffine> = Lazy::new(|| {
+    (0u32..)
+        .find_map(|counter| {
+            let mut input = BLINDING_GENERATOR_DOMAIN.to_vec();
+            input.extend(counter.to_le_bytes());
+            let y: Fq = sha256_bytes_to_field(input);
+            EdwardsAffine::get_point_from_y_unchecked(y, false)
+                .map(|point| point.clear_cofactor())
+                .filter(|point| !point.is_zero())
+        })
+        .unwrap()
+});
+
+/// `G`, the generator values are committed with
+pub fn value_generator() -> EdwardsAffine {
+    EdwardsAffine::generator()
+}
+/// `H`, the generator blinding factors are committed with
+pub fn blinding_generator() -> EdwardsAffine {
+    *BLINDING_GENERATOR
+}
+pub fn random_blinding() -> ScalarField {
+    ScalarField::rand(&mut OsRng)
+}
+/// Signed values are mapped into the scalar field, negative values wrap around the modulus
+pub fn value_to_scalar(value: i64) -> ScalarField {
+    let magnitude = ScalarField::from(value.unsigned_abs());
+    if value < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// A Pedersen commitment `value * G + blinding * H` on the curve embedded in BN254.
+///
+/// Commitments are additively homomorphic: adding two commitments commits to the sum of the values under the sum of
+/// the blindings, which is what lets an organization commit to its epoch delta by adding up transaction commitments.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct ValueCommitment {
+    #[serde(with = "hex")]
+    point: EdwardsAffine,
+}
+impl ValueCommitment {
+    pub fn new(value: i64, blinding: &ScalarField) -> Self {
+        Self {
+            point: (value_generator() * value_to_scalar(value) + blinding_generator() * blinding)
+                .into_affine(),
+        }
+    }
+    /// The commitment to zero with zero blinding, the identity for adding commitments
+    pub fn zero() -> Self {
+        Self {
+            point: EdwardsAffine::zero(),
+        }
+    }
+    pub fn from_point(point: EdwardsAffine) -> Self {
+        Self { point }
+    }
+    pub fn point(&self) -> &EdwardsAffine {
+        &self.point
+    }
+    pub fn opens_to(&self, value: i64, blinding: &ScalarField) -> bool {
+        *self == Self::new(value, blinding)
+    }
+}
+impl Default for ValueCommitment {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+impl Add for ValueCommitment {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            point: (self.point + rhs.point).into_affine(),
+        }
+    }
+}
+impl Sub for ValueCommitment {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            point: (self.point.into_group() - rhs.point).into_affine(),
+        }
+    }
+}
+impl Neg for ValueCommitment {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { point: -self.point }
+    }
+}
+impl Sum for ValueCommitment {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), Add::add)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitments_are_homomorphic() {
+        let (first_blinding, second_blinding) = (random_blinding(), random_blinding());
+        let first = ValueCommitment::new(40, &first_blinding);
+        let second = ValueCommitment::new(15, &second_blinding);
+
+        assert!(first.opens_to(40, &first_blinding));
+        assert!(!first.opens_to(41, &first_blinding));
+        assert!((first - second).opens_to(25, &(first_blinding - second_blinding)));
+        assert!((second - first).opens_to(-25, &(second_blinding - first_blinding)));
+        assert_eq!(first - first, ValueCommitment::zero());
+    }
+}
@@ -34,6 +34,15 @@ pub struct RunConfig {
     pub org_count: usize,
     pub transaction_count: usize,
     pub addresses_per_organization: usize,
+    /// Number of worker threads used when built with the `parallel` feature, `0` lets rayon use one per core
+    #[serde(default)]
+    pub thread_count: usize,
+    /// Fee charged on every simulated transaction and credited to an operator organization, `0` disables fees
+    #[serde(default)]
+    pub transaction_fee: u64,
+    /// Maximum number of transactions per block, `0` puts every transaction in a block of its own
+    #[serde(default)]
+    pub block_size: usize,
 }
 impl RunConfig {
     pub fn create() -> Self {
@@ -45,19 +54,33 @@ impl RunConfig {
             org_count: config_data.org_count,
             transaction_count: config_data.transaction_count,
             addresses_per_organization: config_data.addresses_per_organization,
+            thread_count: config_data.thread_count,
+            transaction_fee: config_data.transaction_fee,
+            block_size: config_data.block_size,
         }
     }
+    /// Size the global rayon thread pool according to `thread_count`. This is a no-op without the `parallel` feature.
+    pub fn configure_thread_pool(&self) {
+        #[cfg(feature = "parallel")]
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.thread_count)
+            .build_global()
+            .expect("Couldn't configure the global thread pool");
+    }
 }
 impl fmt::Display for RunConfig {
     #[allow(rust_2018_idioms)]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let _ = write!(f, "Org Count: {}", self.org_count);
         let _ = write!(f, "Transaction Count: {}", self.transaction_count);
-        write!(
+        let _ = write!(
             f,
             "Addresses per Organization: {}",
             self.addresses_per_organization
-        )
+        );
+        let _ = write!(f, "Thread Count: {}", self.thread_count);
+        let _ = write!(f, "Transaction Fee: {}", self.transaction_fee);
+        write!(f, "Block Size: {}", self.block_size)
     }
 }
 #[cfg(test)]
@@ -72,5 +95,8 @@ mod tests {
             "Addresses per Organization: {}",
             RUN_CONFIG.addresses_per_organization
         );
+        println!("Thread Count: {}", RUN_CONFIG.thread_count);
+        println!("Transaction Fee: {}", RUN_CONFIG.transaction_fee);
+        println!("Block Size: {}", RUN_CONFIG.block_size);
     }
 }
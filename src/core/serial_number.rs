@@ -1,40 +1,43 @@
-use ark_crypto_primitives::crh::{
-    sha256::constraints::{Sha256Gadget, UnitVar},
-    CRHSchemeGadget,
-};
-use ark_ff::PrimeField;
-use ark_r1cs_std::{fields::fp::FpVar, ToBytesGadget, ToConstraintFieldGadget};
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
 
-/// A serial number for a transaction.
+use super::wire::hex;
+use crate::utils::sha256_bytes_to_field;
+
+/// Prefix of the nullifier PRF input so it can never collide with another SHA-256 use of the same secret key
+/// (Zcash Sprout prefixes `PRF^sn` the same way)
+pub const NULLIFIER_PRF_TAG: u8 = 0b11;
+
+/// A serial number (nullifier) for a transaction.
 /// According to the zcash paper, the user u first samples ρ, which is a secret value that determines the coin’s serial number as
-/// sn = hash(p)
-/// For our purposes, 'value' encapsulates the result of 'hash(p)'
-/// NOTE: The zcash specification defines their own methodologies for generating spending keys  which are the spiritual successor to the
-/// double-spending protection serial numbers provide
-#[derive(Clone)]
+/// `sn = PRF_sk(ρ)`
+/// where sk is the secret key of the address that owns the coin. Keying the PRF with the spending key means only the owner can
+/// compute the serial number, and a proof of its derivation doubles as a proof of who spent the coin.
+/// For our purposes, 'value' encapsulates the result of `PRF_sk(ρ)`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct TransactionSerialNumber<F: PrimeField> {
-    value: FpVar<F>,
+    #[serde(with = "hex")]
+    value: F,
 }
 impl<F> TransactionSerialNumber<F>
 where
     F: PrimeField,
 {
-    pub fn new(p: FpVar<F>) -> Self {
-        // Use a buffer array instead of a dynamic vector to avoid multiple allocations
-        let bytes = p.to_bytes().unwrap();
-
-        // Avoid allocating a vector, directly use the bytes array
-        let unit_var: UnitVar<F> = UnitVar::default();
-        let sn = Sha256Gadget::evaluate(&unit_var, &bytes)
-            .unwrap()
-            .0
-            .to_constraint_field()
-            .unwrap()[0]
-            .clone();
-
-        Self { value: sn }
+    pub fn new(secret_key: &F, rho: F) -> Self {
+        Self {
+            value: nullifier_prf(secret_key, &rho),
+        }
     }
-    pub fn sn(&self) -> FpVar<F> {
-        self.value.clone()
+    pub fn sn(&self) -> F {
+        self.value
     }
 }
+/// `PRF_sk(ρ) = SHA256(tag || sk || ρ)`, packed back into a field element the same way the gadget does
+pub fn nullifier_prf<F: PrimeField>(secret_key: &F, rho: &F) -> F {
+    let mut input = vec![NULLIFIER_PRF_TAG];
+    input.extend(secret_key.into_bigint().to_bytes_le());
+    input.extend(rho.into_bigint().to_bytes_le());
+    sha256_bytes_to_field(input)
+}
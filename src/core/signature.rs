@@ -0,0 +1,115 @@
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ed_on_bn254::{EdwardsAffine, Fq, Fr as ScalarField};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use once_cell::sync::Lazy;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+use super::wire::hex;
+use crate::utils::{poseidon_config, poseidon_hash};
+
+/// Poseidon parameters used to compute Schnorr challenges. Shared with the in-circuit verifier
+pub static SIGNATURE_POSEIDON_CONFIG: Lazy<PoseidonConfig<Fq>> = Lazy::new(poseidon_config);
+
+/// A Schnorr signature over the Baby Jubjub compatible twisted Edwards curve embedded in BN254 (ed-on-bn254).
+///
+/// The base field of the curve is the BN254 scalar field, so verifying one of these inside a BN254 circuit only
+/// needs native field arithmetic.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct Signature {
+    #[serde(with = "hex")]
+    r: EdwardsAffine,
+    #[serde(with = "hex")]
+    s: ScalarField,
+}
+impl Signature {
+    pub fn new(r: EdwardsAffine, s: ScalarField) -> Self {
+        Self { r, s }
+    }
+    pub fn r(&self) -> &EdwardsAffine {
+        &self.r
+    }
+    pub fn s(&self) -> &ScalarField {
+        &self.s
+    }
+}
+
+/// Map an address secret key onto the curve's scalar field
+pub fn signing_key<F: PrimeField>(secret_key: &F) -> ScalarField {
+    ScalarField::from_le_bytes_mod_order(&secret_key.into_bigint().to_bytes_le())
+}
+/// The verification key belonging to an address secret key, `sk * G`
+pub fn verification_key<F: PrimeField>(secret_key: &F) -> EdwardsAffine {
+    (EdwardsAffine::generator() * signing_key(secret_key)).into_affine()
+}
+/// Messages are elements of the curve's base field, so other fields are re-encoded through their bytes
+pub fn message_from_field<F: PrimeField>(message: &F) -> Fq {
+    Fq::from_le_bytes_mod_order(&message.into_bigint().to_bytes_le())
+}
+/// `e = Poseidon(R, vk, m)`
+pub fn challenge(r: &EdwardsAffine, verification_key: &EdwardsAffine, message: Fq) -> Fq {
+    poseidon_hash(
+        &SIGNATURE_POSEIDON_CONFIG,
+        &[r.x, r.y, verification_key.x, verification_key.y, message],
+    )
+}
+/// The challenge is used as a scalar. Reducing it here matches multiplying by all of its bits in the circuit because
+/// every point we multiply lives in the prime order subgroup
+fn challenge_scalar(challenge: Fq) -> ScalarField {
+    ScalarField::from_le_bytes_mod_order(&challenge.into_bigint().to_bytes_le())
+}
+/// Sign `message` with an address secret key: `R = k * G`, `s = k + e * sk`
+pub fn sign<F: PrimeField>(secret_key: &F, message: Fq) -> Signature {
+    let nonce = ScalarField::rand(&mut OsRng);
+    let r = (EdwardsAffine::generator() * nonce).into_affine();
+    let e = challenge(&r, &verification_key(secret_key), message);
+    Signature::new(r, nonce + challenge_scalar(e) * signing_key(secret_key))
+}
+/// Check `s * G = R + e * vk`
+pub fn verify(verification_key: &EdwardsAffine, message: Fq, signature: &Signature) -> bool {
+    if !verification_key.is_on_curve()
+        || !verification_key.is_in_correct_subgroup_assuming_on_curve()
+        || !signature.r.is_on_curve()
+    {
+        return false;
+    }
+    let e = challenge_scalar(challenge(&signature.r, verification_key, message));
+    EdwardsAffine::generator() * signature.s == signature.r + *verification_key * e
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret_key = Fr::from(1234u64);
+        let message = Fq::from(99u64);
+        let signature = sign(&secret_key, message);
+
+        assert!(verify(&verification_key(&secret_key), message, &signature));
+        assert!(!verify(
+            &verification_key(&secret_key),
+            Fq::from(100u64),
+            &signature
+        ));
+        assert!(!verify(
+            &verification_key(&Fr::from(4321u64)),
+            message,
+            &signature
+        ));
+    }
+}
@@ -1,72 +1,307 @@
-use ark_ff::PrimeField;
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
-use ark_relations::r1cs::ConstraintSystem;
-use rand::thread_rng;
+use ark_bn254::Bn254;
+use ark_crypto_primitives::snark::SNARK;
+use ark_ed_on_bn254::{EdwardsAffine, Fq, Fr as ScalarField};
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_groth16::{r1cs_to_qap::LibsnarkReduction, Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{rngs::OsRng, thread_rng};
+use serde::{Deserialize, Serialize};
 
-use crate::gadgets::merkle_gadget::MerkleTreeGadget;
+use crate::gadgets::{
+    batch_verifier::batch_verify_or_find_invalid,
+    join_split::{
+        join_split_public_inputs, JoinSplitCircuit, JOIN_SPLIT_INPUTS, JOIN_SPLIT_OUTPUTS,
+    },
+    merkle_gadget::MerkleTreeGadget,
+    value_commitment_gadget::{commitment_public_inputs, ValueRangeCircuit},
+};
 
-use super::{address::Address, serial_number::TransactionSerialNumber};
+use super::{
+    address::{Address, PublicAddress},
+    asset::AssetId,
+    note::Note,
+    pedersen::{random_blinding, ValueCommitment},
+    serial_number::TransactionSerialNumber,
+    signature::{self, message_from_field, Signature},
+    wire::hex,
+};
 
-pub struct Transaction<F: PrimeField> {
-    transaction_id: FpVar<F>,
-    value: i32,
-    sender_address: Address<F>, // built from the spending key of the sender
-    receiver_address: Address<F>, // built from the spending key of the receiver
-    serial_number: TransactionSerialNumber<F>,
+/// One output as the shared transaction shows it: the receiver, commitments to the new note and to its value, and a
+/// proof that the value commitment opens to a value in range
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TransactionOutput<F: PrimeField> {
+    #[serde(with = "hex")]
+    owner_public_key: F,
+    #[serde(with = "hex")]
+    note_commitment: F,
+    value_commitment: ValueCommitment,
+    #[serde(with = "hex")]
+    range_proof: Proof<Bn254>, // a `ValueRangeCircuit` proof for the value commitment
 }
-impl<F> Transaction<F>
+impl<F> TransactionOutput<F>
 where
     F: PrimeField,
 {
-    pub fn new(
-        transaction_id: FpVar<F>,
-        value: i32,
-        sender_address: Address<F>,
-        receiver_address: Address<F>,
-        sn_secret: FpVar<F>,
-    ) -> Self {
+    pub fn owner_public_key(&self) -> &F {
+        &self.owner_public_key
+    }
+    pub fn note_commitment(&self) -> F {
+        self.note_commitment
+    }
+    pub fn value_commitment(&self) -> ValueCommitment {
+        self.value_commitment
+    }
+    pub fn range_proof(&self) -> &Proof<Bn254> {
+        &self.range_proof
+    }
+}
+
+/// What only the sender and the receiver of an output learn: the new note and the blinding of its value commitment
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct OutputOpening<F: PrimeField> {
+    note: Note<F>,
+    #[serde(with = "hex")]
+    value_blinding: ScalarField,
+}
+impl<F> OutputOpening<F>
+where
+    F: PrimeField,
+{
+    pub fn new(receiver_public_key: F, asset: AssetId, value: u64) -> Self {
         Self {
-            transaction_id,
-            value,
-            sender_address,
-            receiver_address,
-            serial_number: TransactionSerialNumber::new(sn_secret),
+            note: Note::random(receiver_public_key, asset, value, &mut thread_rng()),
+            value_blinding: random_blinding(),
         }
     }
-    pub fn transaction_id(&self) -> FpVar<F> {
-        self.transaction_id.clone()
+    pub fn note(&self) -> &Note<F> {
+        &self.note
     }
     pub fn value(&self) -> i32 {
-        self.value
+        i32::try_from(self.note.value()).expect("Output value is too large")
+    }
+    pub fn value_blinding(&self) -> ScalarField {
+        self.value_blinding
+    }
+    pub fn value_commitment(&self) -> ValueCommitment {
+        ValueCommitment::new(
+            i64::try_from(self.note.value()).expect("Output value is too large"),
+            &self.value_blinding,
+        )
+    }
+    /// The shared output, proving the value is in range with the network's range proving key
+    pub fn output(&self, range_proving_key: &ProvingKey<Bn254>) -> TransactionOutput<F> {
+        let value_commitment = self.value_commitment();
+        let range_proof = Groth16::<Bn254, LibsnarkReduction>::prove(
+            range_proving_key,
+            ValueRangeCircuit {
+                commitment: value_commitment,
+                value: self.note.value(),
+                blinding: self.value_blinding,
+            },
+            &mut OsRng,
+        )
+        .unwrap();
+        TransactionOutput {
+            owner_public_key: *self.note.owner_public_key(),
+            note_commitment: self.note.commitment(),
+            value_commitment,
+            range_proof,
+        }
     }
-    pub fn value_as_field_element(&self) -> FpVar<F> {
-        let cs = ConstraintSystem::<F>::new_ref();
-        let field_element = F::from(self.value() as u64);
-        FpVar::<F>::new_input(cs, || Ok(field_element)).unwrap()
+    /// Whether this is the opening of `output`. Its range proof keeps the value of an output within a balance, so an
+    /// opening to a larger value can't be one
+    pub fn opens(&self, output: &TransactionOutput<F>) -> bool {
+        self.note.owner_public_key() == output.owner_public_key()
+            && self.note.commitment() == output.note_commitment
+            && i32::try_from(self.note.value()).is_ok()
+            && self.value_commitment() == output.value_commitment
     }
-    pub fn sender_address(&self) -> Address<F> {
+}
+
+/// The part of a transaction everyone sees. It goes on the wire, its root goes into blocks and is what the sender
+/// signs, and it hides which notes are spent and what the outputs are worth.
+///
+/// Shared transactions travel between processes in the canonical encoding of [`crate::core::wire::WireFormat`].
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SharedTransaction<F: PrimeField> {
+    #[serde(with = "hex")]
+    transaction_id: F,
+    asset: AssetId, // every note the transaction spends or creates, and the fee, are of this asset
+    sender_address: PublicAddress<F>,
+    receiver_address: PublicAddress<F>, // the (first) receiver
+    serial_numbers: Vec<TransactionSerialNumber<F>>, // one per input note, or a single one for transfers without inputs
+    #[serde(with = "hex")]
+    input_note_commitments: Vec<F>, // commitments of the sender's notes being spent
+    outputs: Vec<TransactionOutput<F>>,
+    fee: u64, // paid by the sender to the network's fee sink on top of the outputs
+    #[serde(with = "hex")]
+    join_split_proof: Option<Proof<Bn254>>, // a `JoinSplitCircuit` proof, only for transactions spending notes
+    signature: Signature, // the sender's signature over the transaction root
+}
+impl<F> SharedTransaction<F>
+where
+    F: PrimeField,
+{
+    pub fn transaction_id(&self) -> F {
+        self.transaction_id
+    }
+    pub fn asset(&self) -> AssetId {
+        self.asset
+    }
+    pub fn sender_address(&self) -> PublicAddress<F> {
         self.sender_address.clone()
     }
-    pub fn receiver_address(&self) -> Address<F> {
+    pub fn receiver_address(&self) -> PublicAddress<F> {
         self.receiver_address.clone()
     }
-    pub fn serial_number(&self) -> FpVar<F> {
-        self.serial_number.sn()
+    /// The first serial number, which identifies the transaction on the blockchain
+    pub fn serial_number(&self) -> F {
+        self.serial_numbers[0].sn()
+    }
+    pub fn serial_numbers(&self) -> Vec<F> {
+        self.serial_numbers
+            .iter()
+            .map(TransactionSerialNumber::sn)
+            .collect()
+    }
+    /// Commitments of the notes the transaction spends, empty for transfers that don't spend notes
+    pub fn input_note_commitments(&self) -> Vec<F> {
+        self.input_note_commitments.clone()
+    }
+    pub fn outputs(&self) -> &[TransactionOutput<F>] {
+        &self.outputs
+    }
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+    /// The fee is public, so it is committed to without blinding
+    pub fn fee_commitment(&self) -> ValueCommitment {
+        ValueCommitment::new(
+            i64::try_from(self.fee).expect("Fee is too large"),
+            &ScalarField::zero(),
+        )
+    }
+    /// Commitment to the total value leaving the sender: all outputs plus the fee
+    pub fn value_commitment(&self) -> ValueCommitment {
+        self.outputs
+            .iter()
+            .map(TransactionOutput::value_commitment)
+            .sum::<ValueCommitment>()
+            + self.fee_commitment()
+    }
+    pub fn join_split_proof(&self) -> Option<&Proof<Bn254>> {
+        self.join_split_proof.as_ref()
+    }
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+    /// Check that the transaction was signed by the holder of `verification_key`
+    pub fn verify_signature(&self, verification_key: &EdwardsAffine) -> bool {
+        signature::verify(
+            verification_key,
+            message_from_field(&self.root()),
+            &self.signature,
+        )
+    }
+    /// Check the range proof of every output against its value commitment
+    pub fn verify_range_proofs(&self, range_verifying_key: &VerifyingKey<Bn254>) -> bool {
+        let proofs: Vec<_> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                (
+                    output.range_proof.clone(),
+                    commitment_public_inputs(&output.value_commitment),
+                )
+            })
+            .collect();
+        batch_verify_or_find_invalid(range_verifying_key, &proofs).is_empty()
+    }
+    /// The public inputs of the transaction's [`JoinSplitCircuit`] proof, `None` if it spends or creates more notes
+    /// than the circuit has slots for
+    pub fn join_split_public_inputs(&self) -> Option<Vec<Fq>> {
+        if self.input_note_commitments.len() > JOIN_SPLIT_INPUTS
+            || self.outputs.len() > JOIN_SPLIT_OUTPUTS
+            || self.serial_numbers.len() != self.input_note_commitments.len()
+        {
+            return None;
+        }
+        let inputs: Vec<_> = self
+            .input_note_commitments
+            .iter()
+            .zip(self.serial_numbers())
+            .map(|(commitment, serial_number)| {
+                (to_proof_field(commitment), to_proof_field(&serial_number))
+            })
+            .collect();
+        let outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                (
+                    to_proof_field(&output.owner_public_key),
+                    to_proof_field(&output.note_commitment),
+                    output.value_commitment,
+                )
+            })
+            .collect();
+        Some(join_split_public_inputs(
+            to_proof_field(self.sender_address.public_key()),
+            self.asset,
+            self.fee,
+            &inputs,
+            &outputs,
+        ))
+    }
+    /// Check the `JoinSplit` proof of a transaction spending notes: the serial numbers are the nullifiers of the sender's
+    /// input notes, and their values add up to the outputs, as committed to by the value commitments, plus the fee.
+    ///
+    /// Transfers without input notes draw on a serial number of the sender organization's pool and carry no proof,
+    /// the organization's epoch proof accounts for the value they move.
+    pub fn verify_join_split_proof(&self, join_split_verifying_key: &VerifyingKey<Bn254>) -> bool {
+        match (&self.join_split_proof, self.join_split_public_inputs()) {
+            (None, _) => self.input_note_commitments.is_empty(),
+            (Some(_), _) if self.input_note_commitments.is_empty() => false,
+            (Some(proof), Some(public_inputs)) => Groth16::<Bn254, LibsnarkReduction>::verify(
+                join_split_verifying_key,
+                &public_inputs,
+                proof,
+            )
+            .unwrap_or(false),
+            (Some(_), None) => false,
+        }
     }
-    pub fn to_vec(&self) -> Vec<FpVar<F>> {
-        vec![
+    pub fn to_vec(&self) -> Vec<F> {
+        let sender_key = self.sender_address.verification_key();
+        let receiver_key = self.receiver_address.verification_key();
+        let mut leaves = vec![
             self.transaction_id(),
-            self.value_as_field_element(),
-            self.sender_address().public_key().clone(),
-            self.sender_address().secret_key().clone(),
-            self.receiver_address().public_key().clone(),
-            self.receiver_address().secret_key().clone(),
-            self.serial_number(),
-        ]
-    }
-    pub fn root(&self) -> FpVar<F> {
+            *self.sender_address.public_key(),
+            coordinate_to_field(&sender_key.x),
+            coordinate_to_field(&sender_key.y),
+            *self.receiver_address.public_key(),
+            coordinate_to_field(&receiver_key.x),
+            coordinate_to_field(&receiver_key.y),
+            F::from(self.fee),
+            self.asset.to_field(),
+        ];
+        leaves.extend(self.serial_numbers());
+        // Commit to the notes and value commitments too so they are covered by the signature
+        leaves.extend(self.input_note_commitments.iter().copied());
+        for output in &self.outputs {
+            let commitment = output.value_commitment.point();
+            leaves.push(output.owner_public_key);
+            leaves.push(output.note_commitment);
+            leaves.push(coordinate_to_field(&commitment.x));
+            leaves.push(coordinate_to_field(&commitment.y));
+        }
+        leaves
+    }
+    pub fn root(&self) -> F {
         let leaves = self.to_vec();
-        let cs = ConstraintSystem::<F>::new_ref();
 
         for (idx, _) in leaves.iter().enumerate() {
             // Validate each leaf
@@ -75,52 +310,366 @@ where
                 panic!("Cannot get root hash if leaves are not all valid");
             }
         }
-        MerkleTreeGadget::create_root_hash(leaves, cs)
-    }
-    /// This assumes a single split where the remainder is given back to the original person
-    pub fn split_transaction(
-        &self,
-        split_values: Vec<i32>,                  // The values to split into
-        new_receiver_addresses: Vec<Address<F>>, // The new receiver addresses for each split
-        sender_address_secret: FpVar<F>,         // Sender's secret key
-    ) -> Vec<Self> {
-        // Ensure that the split values sum up to the original transaction value
-        let cs = ConstraintSystem::<F>::new_ref();
-        let mut total_split_value = 0;
-
-        for split_value in &split_values {
-            total_split_value += split_value;
-        }
-
-        // Enforce that the sum of the split values equals the original transaction value
-        assert_eq!(total_split_value, self.value());
-
-        // Create the split transactions
-        let mut split_transactions = Vec::new();
-        let mut rng = thread_rng();
+        MerkleTreeGadget::create_root_hash(leaves)
+    }
+}
 
-        let value = Address::new(&sender_address_secret);
-        for (i, split_value) in split_values.into_iter().enumerate() {
-            let new_transaction_id =
-                FpVar::<F>::new_input(cs.clone(), || Ok(F::rand(&mut rng))).unwrap();
+/// A transaction consuming any number of the sender's notes and creating any number of new notes (a `JoinSplit` in
+/// Zcash terms). The simple one-to-one transfer is the special case with a single output.
+///
+/// This is the transaction as the sender holds it: the [`SharedTransaction`] everyone sees, and the notes it spends and
+/// the openings of its outputs, which the sender only hands to the receivers.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Transaction<F: PrimeField> {
+    shared: SharedTransaction<F>,
+    input_notes: Vec<Note<F>>,       // the sender's notes being spent
+    openings: Vec<OutputOpening<F>>, // one per output, in the same order
+}
+impl<F> Transaction<F>
+where
+    F: PrimeField,
+{
+    pub fn new(
+        transaction_id: F,
+        value: i32,
+        sender_address: Address<F>,
+        receiver_address: PublicAddress<F>,
+        rho: F, // the secret that determines the serial number, only the sender's secret key turns it into one
+        range_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        let value = u64::try_from(value).expect("Transaction value can't be negative");
+        let opening = OutputOpening::new(*receiver_address.public_key(), AssetId::NATIVE, value);
+        Self::assemble(
+            transaction_id,
+            AssetId::NATIVE,
+            &sender_address,
+            receiver_address,
+            vec![TransactionSerialNumber::new(
+                sender_address.secret_key(),
+                rho,
+            )],
+            Vec::new(),
+            vec![opening],
+            0,
+            range_proving_key,
+        )
+        .signed(&sender_address)
+    }
+    /// Charge the sender `fee` on top of the transaction value, signing again with the sender's address. Transactions
+    /// spending notes pay their fee out of the inputs instead, see [`Self::join_split`].
+    pub fn with_fee(mut self, fee: u64, sender_address: &Address<F>) -> Self {
+        assert!(
+            self.input_notes.is_empty(),
+            "Fees of note-spending transactions are set when the transaction is built"
+        );
+        self.shared.fee = fee;
+        self.signed(sender_address)
+    }
+    /// Transfer `asset` instead of the native asset, signing again with the sender's address. Transactions spending
+    /// notes move the asset of their inputs.
+    pub fn with_asset(
+        mut self,
+        asset: AssetId,
+        sender_address: &Address<F>,
+        range_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        assert!(
+            self.input_notes.is_empty(),
+            "Note-spending transactions move the asset of their input notes"
+        );
+        self.shared.asset = asset;
+        self.openings = self
+            .openings
+            .iter()
+            .map(|opening| {
+                OutputOpening::new(
+                    *opening.note().owner_public_key(),
+                    asset,
+                    opening.note().value(),
+                )
+            })
+            .collect();
+        self.shared.outputs = self
+            .openings
+            .iter()
+            .map(|opening| opening.output(range_proving_key))
+            .collect();
+        self.signed(sender_address)
+    }
+    /// Spend a note owned by the sender. The note's nullifier becomes the serial number, `value` goes to the receiver in
+    /// a new note, `fee` goes to the fee sink and whatever is left comes back to the sender as change
+    #[allow(clippy::too_many_arguments)]
+    pub fn spend_note(
+        transaction_id: F,
+        input_note: &Note<F>,
+        value: i32,
+        fee: u64,
+        sender_address: Address<F>,
+        receiver_address: PublicAddress<F>,
+        range_proving_key: &ProvingKey<Bn254>,
+        join_split_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        let value_spent = u64::try_from(value).expect("Transaction value can't be negative");
+        let change = input_note
+            .value()
+            .checked_sub(value_spent + fee)
+            .expect("Note does not hold enough value");
 
-            // Create a new serial number for the split transaction
-            let new_serial_number = TransactionSerialNumber::new(
-                FpVar::new_input(cs.clone(), || Ok(F::rand(&mut rng))).unwrap(),
+        let mut outputs = vec![(receiver_address, value_spent)];
+        if change > 0 {
+            outputs.push((sender_address.public_address(), change));
+        }
+        Self::join_split(
+            transaction_id,
+            sender_address,
+            vec![input_note.clone()],
+            outputs,
+            fee,
+            range_proving_key,
+            join_split_proving_key,
+        )
+    }
+    /// Spend several of the sender's notes at once and split their value over several receivers. The input values
+    /// have to add up to the output values plus the fee exactly, pay any change back to the sender as one of the outputs.
+    ///
+    /// The transaction carries a [`JoinSplitCircuit`] proof made with `join_split_proving_key`, so it spends at most
+    /// [`JOIN_SPLIT_INPUTS`] notes and creates at most [`JOIN_SPLIT_OUTPUTS`].
+    pub fn join_split(
+        transaction_id: F,
+        sender_address: Address<F>,
+        input_notes: Vec<Note<F>>,
+        outputs: Vec<(PublicAddress<F>, u64)>,
+        fee: u64,
+        range_proving_key: &ProvingKey<Bn254>,
+        join_split_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        assert!(
+            (1..=JOIN_SPLIT_INPUTS).contains(&input_notes.len()),
+            "A transaction has to spend between one and {JOIN_SPLIT_INPUTS} notes"
+        );
+        assert!(
+            (1..=JOIN_SPLIT_OUTPUTS).contains(&outputs.len()),
+            "A transaction has to create between one and {JOIN_SPLIT_OUTPUTS} notes"
+        );
+        let asset = input_notes[0].asset();
+        for note in &input_notes {
+            assert_eq!(
+                note.owner_public_key(),
+                sender_address.public_key(),
+                "Only the owner of a note can spend it"
+            );
+            assert_eq!(
+                note.asset(),
+                asset,
+                "A transaction can only spend notes of one asset"
             );
-
-            // Generate the new split transaction
-            let split_transaction = Transaction {
-                transaction_id: new_transaction_id,
-                value: split_value,
-                sender_address: value.clone(), // Sender remains the same
-                receiver_address: new_receiver_addresses[i].clone(), // New receiver address for this split
-                serial_number: new_serial_number,
-            };
-
-            split_transactions.push(split_transaction);
         }
+        let input_value: u64 = input_notes.iter().map(Note::value).sum();
+        let output_value: u64 = outputs.iter().map(|(_, value)| value).sum();
+        assert_eq!(
+            input_value,
+            output_value + fee,
+            "Input notes don't carry the value of the outputs plus the fee"
+        );
 
-        split_transactions
+        let serial_numbers = input_notes
+            .iter()
+            .map(|note| TransactionSerialNumber::new(sender_address.secret_key(), *note.rho()))
+            .collect();
+        let openings = outputs
+            .iter()
+            .map(|(receiver, value)| OutputOpening::new(*receiver.public_key(), asset, *value))
+            .collect();
+        Self::assemble(
+            transaction_id,
+            asset,
+            &sender_address,
+            outputs[0].0.clone(),
+            serial_numbers,
+            input_notes,
+            openings,
+            fee,
+            range_proving_key,
+        )
+        .proved(&sender_address, join_split_proving_key)
+        .signed(&sender_address)
+    }
+    /// Prove the outputs and put the shared transaction together, without a `JoinSplit` proof or a signature yet
+    #[allow(clippy::too_many_arguments)]
+    fn assemble(
+        transaction_id: F,
+        asset: AssetId,
+        sender_address: &Address<F>,
+        receiver_address: PublicAddress<F>,
+        serial_numbers: Vec<TransactionSerialNumber<F>>,
+        input_notes: Vec<Note<F>>,
+        openings: Vec<OutputOpening<F>>,
+        fee: u64,
+        range_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        Self {
+            shared: SharedTransaction {
+                transaction_id,
+                asset,
+                sender_address: sender_address.public_address(),
+                receiver_address,
+                serial_numbers,
+                input_note_commitments: input_notes.iter().map(Note::commitment).collect(),
+                outputs: openings
+                    .iter()
+                    .map(|opening| opening.output(range_proving_key))
+                    .collect(),
+                fee,
+                join_split_proof: None,
+                signature: Signature::default(),
+            },
+            input_notes,
+            openings,
+        }
+    }
+    /// Prove the spend of the input notes with the sender's secret key
+    fn proved(
+        mut self,
+        sender_address: &Address<F>,
+        join_split_proving_key: &ProvingKey<Bn254>,
+    ) -> Self {
+        let proof = Groth16::<Bn254, LibsnarkReduction>::prove(
+            join_split_proving_key,
+            self.join_split_circuit(sender_address),
+            &mut OsRng,
+        )
+        .unwrap();
+        self.shared.join_split_proof = Some(proof);
+        self
+    }
+    /// Sign the transaction root with the sender's secret key
+    fn signed(mut self, sender_address: &Address<F>) -> Self {
+        assert_eq!(
+            sender_address.public_key(),
+            self.shared.sender_address.public_key(),
+            "Only the sender can sign a transaction"
+        );
+        self.shared.signature = signature::sign(
+            sender_address.secret_key(),
+            message_from_field(&self.root()),
+        );
+        self
+    }
+    /// The part of the transaction everyone gets to see
+    pub fn shared(&self) -> &SharedTransaction<F> {
+        &self.shared
+    }
+    pub fn transaction_id(&self) -> F {
+        self.shared.transaction_id
+    }
+    pub fn asset(&self) -> AssetId {
+        self.shared.asset
+    }
+    /// How much leaves the sender, i.e. everything except change paid back to the sender
+    pub fn value(&self) -> i32 {
+        self.openings
+            .iter()
+            .filter(|opening| {
+                opening.note().owner_public_key() != self.shared.sender_address.public_key()
+            })
+            .map(OutputOpening::value)
+            .sum()
+    }
+    pub fn sender_address(&self) -> PublicAddress<F> {
+        self.shared.sender_address()
+    }
+    pub fn receiver_address(&self) -> PublicAddress<F> {
+        self.shared.receiver_address()
+    }
+    /// The first serial number, which identifies the transaction on the blockchain
+    pub fn serial_number(&self) -> F {
+        self.shared.serial_number()
+    }
+    pub fn serial_numbers(&self) -> Vec<F> {
+        self.shared.serial_numbers()
+    }
+    /// The notes the transaction spends, empty for transfers that don't spend notes
+    pub fn input_notes(&self) -> &[Note<F>] {
+        &self.input_notes
     }
+    /// Commitments of the notes the transaction spends, empty for transfers that don't spend notes
+    pub fn input_note_commitments(&self) -> Vec<F> {
+        self.shared.input_note_commitments()
+    }
+    pub fn outputs(&self) -> &[TransactionOutput<F>] {
+        self.shared.outputs()
+    }
+    /// The openings of the outputs, in the same order
+    pub fn openings(&self) -> &[OutputOpening<F>] {
+        &self.openings
+    }
+    pub fn output_notes(&self) -> Vec<Note<F>> {
+        self.openings
+            .iter()
+            .map(|opening| opening.note().clone())
+            .collect()
+    }
+    pub fn fee(&self) -> u64 {
+        self.shared.fee
+    }
+    /// The fee is public, so it is committed to without blinding
+    pub fn fee_commitment(&self) -> ValueCommitment {
+        self.shared.fee_commitment()
+    }
+    /// Commitment to the total value leaving the sender: all outputs plus the fee
+    pub fn value_commitment(&self) -> ValueCommitment {
+        self.shared.value_commitment()
+    }
+    /// The witness for proving the transaction with a [`JoinSplitCircuit`], which only the sender can build
+    pub fn join_split_circuit(&self, sender_address: &Address<F>) -> JoinSplitCircuit {
+        assert_eq!(
+            sender_address.public_key(),
+            self.shared.sender_address.public_key(),
+            "Only the sender can prove a transaction"
+        );
+        JoinSplitCircuit::new(
+            to_proof_field(sender_address.secret_key()),
+            self.input_notes.iter().map(note_to_proof_field).collect(),
+            self.openings
+                .iter()
+                .map(|opening| {
+                    (
+                        note_to_proof_field(opening.note()),
+                        opening.value_blinding(),
+                    )
+                })
+                .collect(),
+            self.shared.fee,
+            self.shared.asset,
+        )
+    }
+    pub fn signature(&self) -> &Signature {
+        self.shared.signature()
+    }
+    /// Check that the transaction was signed by the holder of `verification_key`
+    pub fn verify_signature(&self, verification_key: &EdwardsAffine) -> bool {
+        self.shared.verify_signature(verification_key)
+    }
+    pub fn root(&self) -> F {
+        self.shared.root()
+    }
+}
+/// Re-encode a coordinate of the embedded curve (a BN254 scalar) as an element of `F`
+fn coordinate_to_field<F: PrimeField>(coordinate: &Fq) -> F {
+    F::from_le_bytes_mod_order(&coordinate.into_bigint().to_bytes_le())
+}
+/// Re-encode an element of `F` as a BN254 scalar, the field every proof is over. `F` is that same field in practice,
+/// which makes this the identity
+fn to_proof_field<F: PrimeField>(element: &F) -> Fq {
+    Fq::from_le_bytes_mod_order(&element.into_bigint().to_bytes_le())
+}
+fn note_to_proof_field<F: PrimeField>(note: &Note<F>) -> Note<Fq> {
+    Note::new(
+        to_proof_field(note.owner_public_key()),
+        note.asset(),
+        note.value(),
+        to_proof_field(note.rho()),
+        to_proof_field(note.randomness()),
+    )
 }
@@ -0,0 +1,345 @@
+use std::fmt::Display;
+
+use ark_ff::{BigInteger, PrimeField};
+use rand::seq::IteratorRandom;
+
+use super::{
+    address::{Address, PublicAddress},
+    asset::AssetId,
+    network::Network,
+    org::Organization,
+    serial_number::TransactionSerialNumber,
+    transaction::Transaction,
+};
+use crate::utils::sha256_bytes_to_field;
+
+/// Domain separator for deriving transaction ids
+const TRANSACTION_ID_DOMAIN: &[u8] = b"ducat/transaction-id/v1";
+
+/// Why a [`TransactionBuilder`] couldn't build a transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionError {
+    /// Neither a sender address nor a sending organization was given
+    MissingSender,
+    /// Neither a receiver address nor a receiving organization was given
+    MissingReceiver,
+    /// No organization on the network has this identifier
+    UnknownOrganization(String),
+    /// The sender's address isn't registered with any organization on the network
+    UnknownSender,
+    /// The receiver's address is neither registered with an organization nor an external wallet on the network
+    UnknownReceiver,
+    /// Values have to be positive and fit the 32 bit range the epoch circuit proves
+    InvalidValue(u64),
+    /// Fees, and fees added to the value, have to fit the same 32 bit range
+    InvalidFee(u64),
+    /// The sender's organization doesn't hold enough of the asset to cover the value and the fee
+    InsufficientBalance { available: i64, required: i64 },
+    /// The transaction pays a fee but the network has no fee sink to credit it to
+    NoFeeSink,
+    /// Every serial number of the sender's address has already been used
+    NoUnusedSerialNumber,
+}
+impl Display for TransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingSender => write!(f, "no sender given"),
+            Self::MissingReceiver => write!(f, "no receiver given"),
+            Self::UnknownOrganization(identifier) => {
+                write!(f, "organization {identifier} is not part of the network")
+            }
+            Self::UnknownSender => write!(f, "sender address is not part of the network"),
+            Self::UnknownReceiver => write!(f, "receiver address is not part of the network"),
+            Self::InvalidValue(value) => write!(f, "invalid transaction value {value}"),
+            Self::InvalidFee(fee) => write!(f, "invalid transaction fee {fee}"),
+            Self::InsufficientBalance {
+                available,
+                required,
+            } => write!(
+                f,
+                "sender holds {available} but the transaction needs {required}"
+            ),
+            Self::NoFeeSink => write!(f, "transaction pays a fee but the network has no fee sink"),
+            Self::NoUnusedSerialNumber => write!(f, "sender has no unused serial numbers left"),
+        }
+    }
+}
+impl std::error::Error for TransactionError {}
+
+/// Builds a transfer between two organizations on a [`Network`], checking it can go through before it is signed.
+///
+/// The transaction id is derived from the transaction's contents and the serial number comes out of the sender
+/// organization's pool, so callers only pick the parties and the amount:
+///
+/// ```ignore
+/// let transaction = TransactionBuilder::new(&network)
+///     .from_organization("org1")
+///     .to_organization("org2")
+///     .value(30)
+///     .build()?;
+/// network.send_openings(&transaction);
+/// network.forward_transaction(transaction.shared().clone());
+/// ```
+pub struct TransactionBuilder<'a, F: PrimeField> {
+    network: &'a Network<F>,
+    sender: Option<Address<F>>,
+    receiver: Option<PublicAddress<F>>,
+    sender_organization: Option<String>,
+    receiver_organization: Option<String>,
+    value: u64,
+    asset: AssetId,
+    fee: u64,
+}
+impl<'a, F> TransactionBuilder<'a, F>
+where
+    F: PrimeField,
+{
+    pub fn new(network: &'a Network<F>) -> Self {
+        Self {
+            network,
+            sender: None,
+            receiver: None,
+            sender_organization: None,
+            receiver_organization: None,
+            value: 0,
+            asset: AssetId::NATIVE,
+            fee: 0,
+        }
+    }
+    pub fn sender(mut self, address: Address<F>) -> Self {
+        self.sender = Some(address);
+        self
+    }
+    pub fn receiver(mut self, address: PublicAddress<F>) -> Self {
+        self.receiver = Some(address);
+        self
+    }
+    /// Send from a random address of the organization that still has unused serial numbers
+    pub fn from_organization(mut self, identifier: &str) -> Self {
+        self.sender_organization = Some(identifier.to_owned());
+        self
+    }
+    /// Send to a random address of the organization
+    pub fn to_organization(mut self, identifier: &str) -> Self {
+        self.receiver_organization = Some(identifier.to_owned());
+        self
+    }
+    pub fn value(mut self, value: u64) -> Self {
+        self.value = value;
+        self
+    }
+    pub fn asset(mut self, asset: AssetId) -> Self {
+        self.asset = asset;
+        self
+    }
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+    pub fn build(self) -> Result<Transaction<F>, TransactionError> {
+        let sender = self.sender_address()?;
+        let receiver = self.receiver_address()?;
+        let sender_org = self
+            .network
+            .organization_of(sender.public_key())
+            .ok_or(TransactionError::UnknownSender)?;
+        if !self.network.is_known_address(receiver.public_key()) {
+            return Err(TransactionError::UnknownReceiver);
+        }
+
+        let value = i32::try_from(self.value)
+            .ok()
+            .filter(|value| *value > 0)
+            .ok_or(TransactionError::InvalidValue(self.value))?;
+        let required = i32::try_from(self.fee)
+            .ok()
+            .and_then(|fee| value.checked_add(fee))
+            .ok_or(TransactionError::InvalidFee(self.fee))?;
+        let available = sender_org.available_balance(self.asset) - self.pending_spend(sender_org);
+        if available < i64::from(required) {
+            return Err(TransactionError::InsufficientBalance {
+                available,
+                required: i64::from(required),
+            });
+        }
+        if self.fee > 0 && self.network.fee_sink().is_none() {
+            return Err(TransactionError::NoFeeSink);
+        }
+
+        let rho = self
+            .unused_rho(sender_org, &sender)
+            .ok_or(TransactionError::NoUnusedSerialNumber)?;
+        let serial_number = TransactionSerialNumber::new(sender.secret_key(), rho).sn();
+        let transaction_id = self.transaction_id(&sender, &receiver, serial_number);
+        let range_proving_key = self.network.range_proving_key();
+        let mut transaction = Transaction::new(
+            transaction_id,
+            value,
+            sender.clone(),
+            receiver,
+            rho,
+            range_proving_key,
+        );
+        // Changing the asset proves the outputs again, so only do it when there is something to change
+        if self.asset != AssetId::NATIVE {
+            transaction = transaction.with_asset(self.asset, &sender, range_proving_key);
+        }
+        Ok(transaction.with_fee(self.fee, &sender))
+    }
+    fn sender_address(&self) -> Result<Address<F>, TransactionError> {
+        if let Some(address) = &self.sender {
+            return Ok(address.clone());
+        }
+        let identifier = self
+            .sender_organization
+            .as_ref()
+            .ok_or(TransactionError::MissingSender)?;
+        let org = self
+            .network
+            .organization(identifier)
+            .ok_or_else(|| TransactionError::UnknownOrganization(identifier.clone()))?;
+        org.known_addresses()
+            .into_iter()
+            .filter(|address| self.unused_rho(org, address).is_some())
+            .choose(&mut rand::thread_rng())
+            .ok_or(TransactionError::NoUnusedSerialNumber)
+    }
+    fn receiver_address(&self) -> Result<PublicAddress<F>, TransactionError> {
+        if let Some(address) = &self.receiver {
+            return Ok(address.clone());
+        }
+        let identifier = self
+            .receiver_organization
+            .as_ref()
+            .ok_or(TransactionError::MissingReceiver)?;
+        self.network
+            .organization(identifier)
+            .ok_or_else(|| TransactionError::UnknownOrganization(identifier.clone()))?
+            .known_addresses()
+            .iter()
+            .map(Address::public_address)
+            .choose(&mut rand::thread_rng())
+            .ok_or(TransactionError::UnknownReceiver)
+    }
+    /// Value and fees the organization's transactions waiting in the mempool take out of the asset. The values come out
+    /// of the openings the organization kept of its outputs, change paid back to the sender doesn't count
+    fn pending_spend(&self, org: &Organization<F>) -> i64 {
+        self.network
+            .mempool()
+            .pending()
+            .filter(|t| t.asset() == self.asset)
+            .filter(|t| {
+                self.network
+                    .organization_of(t.sender_address().public_key())
+                    .is_some_and(|sender_org| sender_org.identifier() == org.identifier())
+            })
+            .map(|t| {
+                let fee = i32::try_from(t.fee()).expect("Fees in the mempool fit 32 bits");
+                let value: i64 = t
+                    .outputs()
+                    .iter()
+                    .filter(|output| output.owner_public_key() != t.sender_address().public_key())
+                    .filter_map(|output| org.opening(output))
+                    .map(|opening| i64::from(opening.value()))
+                    .sum();
+                value + i64::from(fee)
+            })
+            .sum()
+    }
+    /// The first ρ of the address that is neither used nor spent by a transaction waiting in the mempool
+    fn unused_rho(&self, org: &Organization<F>, address: &Address<F>) -> Option<F> {
+        org.unused_rhos(address).into_iter().find(|rho| {
+            let serial_number = TransactionSerialNumber::new(address.secret_key(), *rho).sn();
+            !self.network.mempool().is_pending(&serial_number)
+        })
+    }
+    /// `tid = SHA256(domain || sender_pk || receiver_pk || asset || value || fee || sn)`. The serial number is unique,
+    /// so two transactions never share an id
+    fn transaction_id(
+        &self,
+        sender: &Address<F>,
+        receiver: &PublicAddress<F>,
+        serial_number: F,
+    ) -> F {
+        let mut input = TRANSACTION_ID_DOMAIN.to_vec();
+        input.extend(sender.public_key().into_bigint().to_bytes_le());
+        input.extend(receiver.public_key().into_bigint().to_bytes_le());
+        input.extend(self.asset.to_le_bytes());
+        input.extend(self.value.to_le_bytes());
+        input.extend(self.fee.to_le_bytes());
+        input.extend(serial_number.into_bigint().to_bytes_le());
+        sha256_bytes_to_field(input)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::network::fixtures::two_organization_network;
+    use ark_bn254::Fr;
+
+    fn build(network: &Network<Fr>, value: u64) -> TransactionBuilder<'_, Fr> {
+        TransactionBuilder::new(network)
+            .from_organization("org1")
+            .to_organization("org2")
+            .value(value)
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let (mut network, ..) = two_organization_network(2);
+
+        assert_eq!(
+            build(&network, 0).build().err(),
+            Some(TransactionError::InvalidValue(0))
+        );
+        assert_eq!(
+            build(&network, 101).build().err(),
+            Some(TransactionError::InsufficientBalance {
+                available: 100,
+                required: 101
+            })
+        );
+        assert_eq!(
+            build(&network, 10).fee(u64::MAX).build().err(),
+            Some(TransactionError::InvalidFee(u64::MAX))
+        );
+        assert_eq!(
+            build(&network, i32::MAX as u64).fee(1).build().err(),
+            Some(TransactionError::InvalidFee(1))
+        );
+        assert_eq!(
+            build(&network, 10).fee(1).build().err(),
+            Some(TransactionError::NoFeeSink)
+        );
+        assert_eq!(
+            build(&network, 10).to_organization("org3").build().err(),
+            Some(TransactionError::UnknownOrganization("org3".to_owned()))
+        );
+        assert_eq!(
+            TransactionBuilder::new(&network)
+                .from_organization("org1")
+                .receiver(Address::new(Fr::from(5u64)).public_address())
+                .value(10)
+                .build()
+                .err(),
+            Some(TransactionError::UnknownReceiver)
+        );
+
+        // Transactions drawn from the pool get distinct serial numbers and ids until the pool runs dry
+        let mut ids = Vec::new();
+        for _ in 0..20 {
+            let transaction = build(&network, 1).build().unwrap();
+            ids.push(transaction.transaction_id());
+            network.send_openings(&transaction);
+            network.forward_transaction(transaction.shared().clone());
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 20);
+        assert_eq!(network.organizations()["org1"].delta(AssetId::NATIVE), -20);
+        assert_eq!(
+            build(&network, 1).build().err(),
+            Some(TransactionError::NoUnusedSerialNumber)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Display;
+
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{
+    address::PublicAddress, block::Block, serial_number::TransactionSerialNumber,
+    transaction::SharedTransaction,
+};
+
+/// Version of the wire format. Bump it whenever the encoding of any wire type changes so old bytes are rejected
+/// instead of being misread
+pub const WIRE_FORMAT_VERSION: u8 = 4;
+
+/// Why bytes or JSON couldn't be decoded
+#[derive(Debug)]
+pub enum WireError {
+    /// The encoding was made with a different version of the wire format
+    UnsupportedVersion(u8),
+    /// There were no bytes to read the version from
+    Empty,
+    /// The canonical binary encoding is malformed, e.g. a point that isn't on the curve
+    Binary(SerializationError),
+    /// The JSON form is malformed, including hex strings that don't decode to a valid value
+    Json(serde_json::Error),
+}
+impl Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "wire format version {version} is not supported, expected {WIRE_FORMAT_VERSION}"
+            ),
+            Self::Empty => write!(f, "no bytes to decode"),
+            Self::Binary(error) => write!(f, "malformed binary encoding: {error}"),
+            Self::Json(error) => write!(f, "malformed JSON encoding: {error}"),
+        }
+    }
+}
+impl std::error::Error for WireError {}
+
+/// The JSON form of a wire type, tagged with the format version
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u8,
+    payload: T,
+}
+
+/// Canonical encodings of the types exchanged between processes (transactions, public addresses, serial numbers).
+///
+/// The binary form is the wire format version followed by the compressed ark-serialize encoding. The JSON form
+/// mirrors the type's fields with field elements and curve points written as hex of their compressed encoding.
+pub trait WireFormat:
+    CanonicalSerialize + CanonicalDeserialize + Serialize + DeserializeOwned
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![WIRE_FORMAT_VERSION];
+        self.serialize_compressed(&mut bytes)
+            .expect("Writing to a vector can't fail");
+        bytes
+    }
+    fn from_bytes(bytes: &[u8]) -> Result<Self, WireError> {
+        let (version, mut body) = bytes.split_first().ok_or(WireError::Empty)?;
+        if *version != WIRE_FORMAT_VERSION {
+            return Err(WireError::UnsupportedVersion(*version));
+        }
+        let value = Self::deserialize_compressed(&mut body).map_err(WireError::Binary)?;
+        if !body.is_empty() {
+            return Err(WireError::Binary(SerializationError::InvalidData));
+        }
+        Ok(value)
+    }
+    fn to_json(&self) -> String {
+        serde_json::to_string(&Envelope {
+            version: WIRE_FORMAT_VERSION,
+            payload: self,
+        })
+        .expect("Wire types always serialize")
+    }
+    fn from_json(json: &str) -> Result<Self, WireError> {
+        let envelope: Envelope<Self> = serde_json::from_str(json).map_err(WireError::Json)?;
+        if envelope.version != WIRE_FORMAT_VERSION {
+            return Err(WireError::UnsupportedVersion(envelope.version));
+        }
+        Ok(envelope.payload)
+    }
+    /// SHA-256 of the canonical bytes. Equal values always hash the same, in this process or any other
+    fn wire_hash(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `#[serde(with = "hex")]` for field elements, curve points and anything else ark-serialize can encode
+pub mod hex {
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut bytes = Vec::new();
+        value
+            .serialize_compressed(&mut bytes)
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&super::encode_hex(&bytes))
+    }
+    pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let bytes = super::decode_hex(&hex).ok_or_else(|| D::Error::custom("invalid hex"))?;
+        T::deserialize_compressed(bytes.as_slice()).map_err(D::Error::custom)
+    }
+}
+
+impl<F: PrimeField> WireFormat for SharedTransaction<F> {}
+impl<F: PrimeField> WireFormat for PublicAddress<F> {}
+impl<F: PrimeField> WireFormat for TransactionSerialNumber<F> {}
+impl<F: PrimeField> WireFormat for Block<F> {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        address::Address,
+        asset::AssetId,
+        network::{fixtures::join_split_keys, shared_range_setup},
+        note::Note,
+        transaction::Transaction,
+    };
+    use ark_bn254::Fr;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_round_trips() {
+        let (range_proving_key, range_verifying_key) = shared_range_setup();
+        let (join_split_key, join_split_verifying_key) = join_split_keys();
+        let sender = Address::new(Fr::from(11u64));
+        let receiver = Address::new(Fr::from(12u64));
+        let note = Note::random(*sender.public_key(), AssetId::new(3), 40, &mut thread_rng());
+        let private = Transaction::spend_note(
+            Fr::from(9u64),
+            &note,
+            25,
+            1,
+            sender.clone(),
+            receiver.public_address(),
+            &range_proving_key,
+            &join_split_key,
+        );
+        let transaction = private.shared();
+
+        let bytes = transaction.to_bytes();
+        assert_eq!(bytes[0], WIRE_FORMAT_VERSION);
+        let decoded = SharedTransaction::<Fr>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.root(), transaction.root());
+        assert!(decoded.verify_signature(sender.verification_key()));
+        assert!(decoded.verify_range_proofs(&range_verifying_key));
+        assert!(decoded.verify_join_split_proof(&join_split_verifying_key));
+
+        let json = transaction.to_json();
+        let decoded = SharedTransaction::<Fr>::from_json(&json).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.to_json(), json);
+
+        // Neither party's secret key, nor the notes and blindings only the parties learn, are encoded or committed to
+        let contains = |secret: &dyn Fn(&mut Vec<u8>)| {
+            let mut encoded = Vec::new();
+            secret(&mut encoded);
+            bytes.windows(encoded.len()).any(|window| window == encoded)
+                || json.contains(&encode_hex(&encoded))
+        };
+        for secret_key in [sender.secret_key(), receiver.secret_key()] {
+            assert!(!contains(&|bytes| secret_key
+                .serialize_compressed(bytes)
+                .unwrap()));
+            assert!(!transaction.to_vec().contains(secret_key));
+        }
+        for opening in private.openings() {
+            assert!(!contains(&|bytes| opening
+                .value_blinding()
+                .serialize_compressed(bytes)
+                .unwrap()));
+            assert!(!contains(&|bytes| opening
+                .note()
+                .randomness()
+                .serialize_compressed(bytes)
+                .unwrap()));
+        }
+        assert!(!contains(&|bytes| note
+            .rho()
+            .serialize_compressed(bytes)
+            .unwrap()));
+
+        let decoded = PublicAddress::<Fr>::from_json(&sender.public_address().to_json()).unwrap();
+        assert_eq!(decoded.public_key(), sender.public_key());
+        assert_eq!(decoded.verification_key(), sender.verification_key());
+        let serial_number = TransactionSerialNumber::new(sender.secret_key(), Fr::from(4u64));
+        let decoded = TransactionSerialNumber::<Fr>::from_bytes(&serial_number.to_bytes()).unwrap();
+        assert_eq!(decoded.sn(), serial_number.sn());
+    }
+
+    #[test]
+    fn test_hash_and_version() {
+        let (range_proving_key, _) = shared_range_setup();
+        let sender = Address::new(Fr::from(1u64));
+        let receiver = Address::new(Fr::from(2u64));
+        let transaction = Transaction::new(
+            Fr::from(1u64),
+            10,
+            sender.clone(),
+            receiver.public_address(),
+            Fr::from(0u64),
+            &range_proving_key,
+        )
+        .shared()
+        .clone();
+        let decoded = SharedTransaction::<Fr>::from_json(&transaction.to_json()).unwrap();
+        assert_eq!(decoded.wire_hash(), transaction.wire_hash());
+        let other = Transaction::new(
+            Fr::from(2u64),
+            10,
+            sender,
+            receiver.public_address(),
+            Fr::from(0u64),
+            &range_proving_key,
+        );
+        assert_ne!(other.shared().wire_hash(), transaction.wire_hash());
+
+        // Other versions, trailing bytes and broken hex are rejected
+        let mut bytes = transaction.to_bytes();
+        bytes[0] = WIRE_FORMAT_VERSION + 1;
+        assert!(matches!(
+            SharedTransaction::<Fr>::from_bytes(&bytes),
+            Err(WireError::UnsupportedVersion(_))
+        ));
+        let mut bytes = transaction.to_bytes();
+        bytes.push(0);
+        assert!(matches!(
+            SharedTransaction::<Fr>::from_bytes(&bytes),
+            Err(WireError::Binary(_))
+        ));
+        let json = transaction
+            .to_json()
+            .replacen("\"version\":4", "\"version\":5", 1);
+        assert!(matches!(
+            SharedTransaction::<Fr>::from_json(&json),
+            Err(WireError::UnsupportedVersion(5))
+        ));
+        assert!(
+            PublicAddress::<Fr>::from_json(r#"{"version":3,"payload":{"public_key":"zz"}}"#)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{
+    pairing::{Pairing, PairingOutput},
+    AffineRepr, CurveGroup, VariableBaseMSM,
+};
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use ark_std::UniformRand;
+use rand::{rngs::StdRng, SeedableRng};
+use rs_merkle::{algorithms::Sha256, Hasher};
+
+/// Domain separator used to derive the (transparent) commitment keys
+const AGGREGATION_KEY_DOMAIN: &[u8] = b"ducat-snarkpack-commitment-key";
+
+/// Commitment keys used by the aggregation argument.
+///
+/// `SnarkPack` uses a structured reference string so that the verifier can check the final commitment keys in
+/// logarithmic time. We instead derive the keys from a public seed (nobody knows any discrete log relation
+/// between them), which needs no trusted setup at the cost of the verifier folding the keys itself in `O(n)`
+/// group operations. The proof itself stays logarithmic in the number of aggregated proofs.
+#[derive(Clone)]
+pub struct AggregationKey {
+    /// Key used to commit to the `A` and `C` elements of the proofs
+    v: Vec<G2Affine>,
+    /// Key used to commit to the `B` elements of the proofs
+    w: Vec<G1Affine>,
+}
+impl AggregationKey {
+    /// Derive the commitment keys for `size` proofs, `size` must be a power of two.
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two());
+        let mut rng = StdRng::from_seed(Sha256::hash(AGGREGATION_KEY_DOMAIN));
+        let v = (0..size).map(|_| G2Affine::rand(&mut rng)).collect();
+        let w = (0..size).map(|_| G1Affine::rand(&mut rng)).collect();
+        Self { v, w }
+    }
+}
+
+/// The messages sent in a single round of the inner product argument.
+///
+/// Each round halves the committed vectors, `left` terms get folded in with the round challenge `x` and `right`
+/// terms with `x^-1`.
+#[derive(Clone)]
+pub struct AggregationRound {
+    ip_ab_left: PairingOutput<Bn254>,
+    ip_ab_right: PairingOutput<Bn254>,
+    com_a_left: PairingOutput<Bn254>,
+    com_a_right: PairingOutput<Bn254>,
+    com_b_left: PairingOutput<Bn254>,
+    com_b_right: PairingOutput<Bn254>,
+    com_c_left: PairingOutput<Bn254>,
+    com_c_right: PairingOutput<Bn254>,
+    agg_c_left: G1Affine,
+    agg_c_right: G1Affine,
+}
+
+/// A `SnarkPack`-style aggregate of many Groth16 proofs for the same circuit.
+///
+/// Given proofs `(A_i, B_i, C_i)` and a random `r`, every proof equation can be combined into
+///
+/// `sum r^i e(A_i, B_i) = e(alpha, beta) * sum(r^i) + e(sum r^i IC_i, gamma) + e(sum r^i C_i, delta)`
+///
+/// The prover sends `Z_AB = sum r^i e(A_i, B_i)` and `Z_C = sum r^i C_i` together with pairing commitments to the
+/// `A`, `B` and `C` vectors and proves with a TIPP (for `Z_AB`) and a MIPP (for `Z_C`) that these were computed
+/// correctly from the committed vectors. Both arguments share one GIPA recursion so the proof has `log(n)` rounds.
+#[derive(Clone)]
+pub struct AggregateProof {
+    proof_count: usize,
+    com_a: PairingOutput<Bn254>,
+    com_b: PairingOutput<Bn254>,
+    com_c: PairingOutput<Bn254>,
+    ip_ab: PairingOutput<Bn254>,
+    agg_c: G1Affine,
+    rounds: Vec<AggregationRound>,
+    final_a: G1Affine,
+    final_b: G2Affine,
+    final_c: G1Affine,
+}
+impl AggregateProof {
+    pub fn proof_count(&self) -> usize {
+        self.proof_count
+    }
+}
+
+/// A minimal Fiat-Shamir transcript built on top of the SHA-256 hasher we already use for Merkle trees
+struct Transcript {
+    state: [u8; 32],
+}
+impl Transcript {
+    fn new(label: &[u8]) -> Self {
+        Self {
+            state: Sha256::hash(label),
+        }
+    }
+    fn append<T: CanonicalSerialize>(&mut self, item: &T) {
+        let mut bytes = self.state.to_vec();
+        item.serialize_compressed(&mut bytes).unwrap();
+        self.state = Sha256::hash(&bytes);
+    }
+    fn challenge(&mut self) -> Fr {
+        self.state = Sha256::hash(&self.state);
+        let challenge = Fr::from_le_bytes_mod_order(&self.state);
+        assert!(!challenge.is_zero(), "Zero Fiat-Shamir challenge");
+        challenge
+    }
+}
+
+fn powers_of(base: Fr, count: usize) -> Vec<Fr> {
+    let mut powers = Vec::with_capacity(count);
+    let mut current = Fr::one();
+    for _ in 0..count {
+        powers.push(current);
+        current *= base;
+    }
+    powers
+}
+
+fn pairing_product(g1: &[G1Affine], g2: &[G2Affine]) -> PairingOutput<Bn254> {
+    Bn254::multi_pairing(g1.iter().copied(), g2.iter().copied())
+}
+
+fn fold_g1(left: &[G1Affine], right: &[G1Affine], scalar: Fr) -> Vec<G1Affine> {
+    let folded: Vec<G1Projective> = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| *l + *r * scalar)
+        .collect();
+    G1Projective::normalize_batch(&folded)
+}
+
+fn fold_g2(left: &[G2Affine], right: &[G2Affine], scalar: Fr) -> Vec<G2Affine> {
+    let folded: Vec<G2Projective> = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| *l + *r * scalar)
+        .collect();
+    G2Projective::normalize_batch(&folded)
+}
+
+fn fold_scalars(left: &[Fr], right: &[Fr], scalar: Fr) -> Vec<Fr> {
+    left.iter()
+        .zip(right)
+        .map(|(l, r)| *l + *r * scalar)
+        .collect()
+}
+
+/// `r^i * IC(x_i)` summed over every aggregated proof, i.e. the public input part of the aggregated equation
+fn aggregate_public_inputs(
+    vk: &VerifyingKey<Bn254>,
+    public_inputs: &[Vec<Fr>],
+    r_powers: &[Fr],
+) -> Option<G1Projective> {
+    let mut accumulated = G1Projective::zero();
+    for (inputs, r_power) in public_inputs.iter().zip(r_powers) {
+        if inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return None;
+        }
+        let mut prepared_inputs = vk.gamma_abc_g1[0].into_group();
+        for (input, base) in inputs.iter().zip(vk.gamma_abc_g1.iter().skip(1)) {
+            prepared_inputs += base.mul_bigint(input.into_bigint());
+        }
+        accumulated += prepared_inputs * r_power;
+    }
+    Some(accumulated)
+}
+
+/// Aggregate Groth16 proofs that share the verifying key `vk` into a single [`AggregateProof`], `public_inputs`
+/// holds the public inputs of each proof in the same order.
+pub fn aggregate_proofs(
+    vk: &VerifyingKey<Bn254>,
+    proofs: &[Proof<Bn254>],
+    public_inputs: &[Vec<Fr>],
+) -> AggregateProof {
+    assert!(
+        !proofs.is_empty(),
+        "Cannot aggregate an empty set of proofs"
+    );
+    assert_eq!(
+        proofs.len(),
+        public_inputs.len(),
+        "Every aggregated proof needs its public inputs"
+    );
+    let proof_count = proofs.len();
+    let size = proof_count.next_power_of_two();
+    let key = AggregationKey::new(size);
+
+    // Pad with the identity, padded entries contribute nothing to any of the inner products
+    let mut a: Vec<G1Affine> = proofs.iter().map(|proof| proof.a).collect();
+    let mut b: Vec<G2Affine> = proofs.iter().map(|proof| proof.b).collect();
+    let mut c: Vec<G1Affine> = proofs.iter().map(|proof| proof.c).collect();
+    a.resize(size, G1Affine::zero());
+    b.resize(size, G2Affine::zero());
+    c.resize(size, G1Affine::zero());
+
+    let com_a = pairing_product(&a, &key.v);
+    let com_b = pairing_product(&key.w, &b);
+    let com_c = pairing_product(&c, &key.v);
+
+    let mut transcript = Transcript::new(b"ducat-snarkpack");
+    transcript.append(vk);
+    transcript.append(&(proof_count as u64));
+    for inputs in public_inputs {
+        transcript.append(inputs);
+    }
+    transcript.append(&com_a);
+    transcript.append(&com_b);
+    transcript.append(&com_c);
+    let r = transcript.challenge();
+    let r_inverse = r.inverse().unwrap();
+
+    // The TIPP runs over `r^i * B_i` with the key `r^-i * w_i`, which keeps the commitment to `B` unchanged
+    let mut r_powers = powers_of(r, size);
+    let r_inverse_powers = powers_of(r_inverse, size);
+    let mut b: Vec<G2Affine> = G2Projective::normalize_batch(
+        &b.iter()
+            .zip(&r_powers)
+            .map(|(element, power)| *element * power)
+            .collect::<Vec<_>>(),
+    );
+    let mut w: Vec<G1Affine> = G1Projective::normalize_batch(
+        &key.w
+            .iter()
+            .zip(&r_inverse_powers)
+            .map(|(element, power)| *element * power)
+            .collect::<Vec<_>>(),
+    );
+    let mut v = key.v;
+
+    let ip_ab = pairing_product(&a, &b);
+    let agg_c = G1Projective::msm(&c, &r_powers).unwrap().into_affine();
+    transcript.append(&ip_ab);
+    transcript.append(&agg_c);
+
+    let mut rounds = Vec::with_capacity(size.trailing_zeros() as usize);
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_left, a_right) = a.split_at(half);
+        let (b_left, b_right) = b.split_at(half);
+        let (c_left, c_right) = c.split_at(half);
+        let (v_left, v_right) = v.split_at(half);
+        let (w_left, w_right) = w.split_at(half);
+        let (r_left, r_right) = r_powers.split_at(half);
+
+        let round = AggregationRound {
+            ip_ab_left: pairing_product(a_right, b_left),
+            ip_ab_right: pairing_product(a_left, b_right),
+            com_a_left: pairing_product(a_right, v_left),
+            com_a_right: pairing_product(a_left, v_right),
+            com_b_left: pairing_product(w_right, b_left),
+            com_b_right: pairing_product(w_left, b_right),
+            com_c_left: pairing_product(c_right, v_left),
+            com_c_right: pairing_product(c_left, v_right),
+            agg_c_left: G1Projective::msm(c_right, r_left).unwrap().into_affine(),
+            agg_c_right: G1Projective::msm(c_left, r_right).unwrap().into_affine(),
+        };
+        let x = round_challenge(&mut transcript, &round);
+        let x_inverse = x.inverse().unwrap();
+
+        let next_a = fold_g1(a_left, a_right, x);
+        let next_b = fold_g2(b_left, b_right, x_inverse);
+        let next_c = fold_g1(c_left, c_right, x);
+        let next_v = fold_g2(v_left, v_right, x_inverse);
+        let next_w = fold_g1(w_left, w_right, x);
+        let next_r = fold_scalars(r_left, r_right, x_inverse);
+        a = next_a;
+        b = next_b;
+        c = next_c;
+        v = next_v;
+        w = next_w;
+        r_powers = next_r;
+        rounds.push(round);
+    }
+
+    AggregateProof {
+        proof_count,
+        com_a,
+        com_b,
+        com_c,
+        ip_ab,
+        agg_c,
+        rounds,
+        final_a: a[0],
+        final_b: b[0],
+        final_c: c[0],
+    }
+}
+
+fn round_challenge(transcript: &mut Transcript, round: &AggregationRound) -> Fr {
+    transcript.append(&round.ip_ab_left);
+    transcript.append(&round.ip_ab_right);
+    transcript.append(&round.com_a_left);
+    transcript.append(&round.com_a_right);
+    transcript.append(&round.com_b_left);
+    transcript.append(&round.com_b_right);
+    transcript.append(&round.com_c_left);
+    transcript.append(&round.com_c_right);
+    transcript.append(&round.agg_c_left);
+    transcript.append(&round.agg_c_right);
+    transcript.challenge()
+}
+
+/// Verify an [`AggregateProof`] against the verifying key shared by the aggregated proofs and the public inputs of
+/// each aggregated proof (in the same order the proofs were aggregated in).
+pub fn verify_aggregate_proof(
+    vk: &VerifyingKey<Bn254>,
+    public_inputs: &[Vec<Fr>],
+    aggregate: &AggregateProof,
+) -> bool {
+    let proof_count = aggregate.proof_count;
+    if proof_count == 0 || public_inputs.len() != proof_count {
+        return false;
+    }
+    let size = proof_count.next_power_of_two();
+    if aggregate.rounds.len() != size.trailing_zeros() as usize {
+        return false;
+    }
+
+    let mut transcript = Transcript::new(b"ducat-snarkpack");
+    transcript.append(vk);
+    transcript.append(&(proof_count as u64));
+    for inputs in public_inputs {
+        transcript.append(inputs);
+    }
+    transcript.append(&aggregate.com_a);
+    transcript.append(&aggregate.com_b);
+    transcript.append(&aggregate.com_c);
+    let r = transcript.challenge();
+    let r_inverse = r.inverse().unwrap();
+    transcript.append(&aggregate.ip_ab);
+    transcript.append(&aggregate.agg_c);
+
+    // Check the aggregated Groth16 equation, only the real (unpadded) proofs contribute to the right hand side
+    let r_powers = powers_of(r, size);
+    let r_sum: Fr = r_powers[..proof_count].iter().sum();
+    let accumulated_inputs =
+        match aggregate_public_inputs(vk, public_inputs, &r_powers[..proof_count]) {
+            Some(accumulated_inputs) => accumulated_inputs,
+            None => return false,
+        };
+    let expected_ip_ab = Bn254::multi_pairing(
+        [
+            (vk.alpha_g1 * r_sum).into_affine(),
+            accumulated_inputs.into_affine(),
+            aggregate.agg_c,
+        ],
+        [vk.beta_g2, vk.gamma_g2, vk.delta_g2],
+    );
+    if expected_ip_ab != aggregate.ip_ab {
+        return false;
+    }
+
+    // Replay the GIPA recursion on the commitments and fold the keys and scalars ourselves
+    let key = AggregationKey::new(size);
+    let mut v = key.v;
+    let mut w: Vec<G1Affine> = G1Projective::normalize_batch(
+        &key.w
+            .iter()
+            .zip(powers_of(r_inverse, size))
+            .map(|(element, power)| *element * power)
+            .collect::<Vec<_>>(),
+    );
+    let mut scalars = r_powers;
+    let mut com_a = aggregate.com_a;
+    let mut com_b = aggregate.com_b;
+    let mut com_c = aggregate.com_c;
+    let mut ip_ab = aggregate.ip_ab;
+    let mut agg_c = aggregate.agg_c.into_group();
+    for round in &aggregate.rounds {
+        let x = round_challenge(&mut transcript, round);
+        let x_inverse = x.inverse().unwrap();
+
+        com_a = com_a + round.com_a_left * x + round.com_a_right * x_inverse;
+        com_b = com_b + round.com_b_left * x + round.com_b_right * x_inverse;
+        com_c = com_c + round.com_c_left * x + round.com_c_right * x_inverse;
+        ip_ab = ip_ab + round.ip_ab_left * x + round.ip_ab_right * x_inverse;
+        agg_c += round.agg_c_left * x + round.agg_c_right * x_inverse;
+
+        let half = v.len() / 2;
+        v = fold_g2(&v[..half], &v[half..], x_inverse);
+        w = fold_g1(&w[..half], &w[half..], x);
+        scalars = fold_scalars(&scalars[..half], &scalars[half..], x_inverse);
+    }
+
+    com_a == Bn254::pairing(aggregate.final_a, v[0])
+        && com_b == Bn254::pairing(w[0], aggregate.final_b)
+        && com_c == Bn254::pairing(aggregate.final_c, v[0])
+        && ip_ab == Bn254::pairing(aggregate.final_a, aggregate.final_b)
+        && agg_c == aggregate.final_c * scalars[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::pedersen::{random_blinding, ValueCommitment},
+        gadgets::epoch_circuit::{epoch_public_inputs, generate_proof, EpochBalanceCircuit},
+    };
+    use ark_crypto_primitives::snark::SNARK;
+    use ark_groth16::{r1cs_to_qap::LibsnarkReduction, Groth16};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_aggregation() {
+        let mut rng = OsRng;
+        let (proving_key, verifying_key) =
+            Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+                EpochBalanceCircuit::blank(),
+                &mut rng,
+            )
+            .unwrap();
+        let (proofs, public_inputs): (Vec<Proof<Bn254>>, Vec<Vec<Fr>>) =
+            [(10, 5, 15), (7, -2, 5), (0, 0, 0)]
+                .into_iter()
+                .map(|(initial, delta, last)| {
+                    let blinding = random_blinding();
+                    let commitment = ValueCommitment::new(i64::from(delta), &blinding);
+                    (
+                        generate_proof(
+                            initial,
+                            delta,
+                            last,
+                            commitment,
+                            blinding,
+                            [0; 32],
+                            &proving_key,
+                        ),
+                        epoch_public_inputs(&commitment, &[0; 32]),
+                    )
+                })
+                .unzip();
+
+        let aggregate = aggregate_proofs(&verifying_key, &proofs, &public_inputs);
+        assert!(verify_aggregate_proof(
+            &verifying_key,
+            &public_inputs,
+            &aggregate
+        ));
+
+        let mut tampered_inputs = public_inputs.clone();
+        tampered_inputs.swap(0, 1);
+        assert!(!verify_aggregate_proof(
+            &verifying_key,
+            &tampered_inputs,
+            &aggregate
+        ));
+
+        let mut tampered = proofs;
+        tampered[2].c = (tampered[2].c + G1Affine::generator()).into_affine();
+        let aggregate = aggregate_proofs(&verifying_key, &tampered, &public_inputs);
+        assert!(!verify_aggregate_proof(
+            &verifying_key,
+            &public_inputs,
+            &aggregate
+        ));
+    }
+}
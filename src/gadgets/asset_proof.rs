@@ -52,7 +52,7 @@ impl AssetProof {
     }
 }
 impl<F: Field> ConstraintSynthesizer<F> for AssetProof {
-    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+    fn generate_constraints(self, _cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
         if !validate_transaction_serial_numbers(
             self.blockchain_sns.clone(),
             self.spent_serial_numbers.clone(),
@@ -0,0 +1,141 @@
+use ark_bn254::{Bn254, Fr, G1Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{
+    prepare_verifying_key, r1cs_to_qap::LibsnarkReduction, Groth16, Proof, VerifyingKey,
+};
+use ark_std::UniformRand;
+use rand::rngs::OsRng;
+
+/// Batch verify many Groth16 proofs that were all produced for the same circuit (and therefore share
+/// a verifying key).
+///
+/// Every proof `i` has to satisfy `e(A_i, B_i) = e(alpha, beta) * e(IC_i, gamma) * e(C_i, delta)`. Instead of
+/// checking each equation on its own, each one is scaled by a fresh random `r_i` and all of them are folded
+/// into a single multi-pairing:
+///
+/// `prod e(r_i * A_i, B_i) * e(-sum(r_i) * alpha, beta) * e(-sum(r_i * IC_i), gamma) * e(-sum(r_i * C_i), delta) = 1`
+///
+/// A single invalid proof makes the product differ from the identity except with negligible probability.
+/// This only tells us *that* something failed, use [`find_invalid_proofs`] to figure out *which* proof it was.
+pub fn batch_verify_proofs(vk: &VerifyingKey<Bn254>, proofs: &[(Proof<Bn254>, Vec<Fr>)]) -> bool {
+    if proofs.is_empty() {
+        return true;
+    }
+    let mut rng = OsRng;
+
+    let mut g1_terms = Vec::with_capacity(proofs.len() + 3);
+    let mut g2_terms = Vec::with_capacity(proofs.len() + 3);
+    let mut randomizer_sum = Fr::zero();
+    let mut accumulated_inputs = G1Projective::zero();
+    let mut accumulated_c = G1Projective::zero();
+
+    for (proof, public_inputs) in proofs {
+        if public_inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return false;
+        }
+        let r = Fr::rand(&mut rng);
+
+        // Same as `Groth16::prepare_inputs` but without needing a prepared verifying key
+        let mut prepared_inputs = vk.gamma_abc_g1[0].into_group();
+        for (input, base) in public_inputs.iter().zip(vk.gamma_abc_g1.iter().skip(1)) {
+            prepared_inputs += base.mul_bigint(input.into_bigint());
+        }
+
+        g1_terms.push((proof.a * r).into_affine());
+        g2_terms.push(proof.b);
+        randomizer_sum += r;
+        accumulated_inputs += prepared_inputs * r;
+        accumulated_c += proof.c * r;
+    }
+
+    g1_terms.push((-(vk.alpha_g1 * randomizer_sum)).into_affine());
+    g2_terms.push(vk.beta_g2);
+    g1_terms.push((-accumulated_inputs).into_affine());
+    g2_terms.push(vk.gamma_g2);
+    g1_terms.push((-accumulated_c).into_affine());
+    g2_terms.push(vk.delta_g2);
+
+    Bn254::multi_pairing(g1_terms, g2_terms).is_zero()
+}
+
+/// Verify each proof on its own and return the indices of the proofs that failed.
+///
+/// This is the fallback for when [`batch_verify_proofs`] rejects a batch.
+pub fn find_invalid_proofs(
+    vk: &VerifyingKey<Bn254>,
+    proofs: &[(Proof<Bn254>, Vec<Fr>)],
+) -> Vec<usize> {
+    let pvk = prepare_verifying_key(vk);
+    proofs
+        .iter()
+        .enumerate()
+        .filter(|(_, (proof, public_inputs))| {
+            !Groth16::<Bn254, LibsnarkReduction>::verify_proof(&pvk, proof, public_inputs)
+                .unwrap_or(false)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Batch verify the proofs and, if the batch is rejected, fall back to per-proof verification to find the
+/// offending proofs. An empty result means every proof is valid.
+pub fn batch_verify_or_find_invalid(
+    vk: &VerifyingKey<Bn254>,
+    proofs: &[(Proof<Bn254>, Vec<Fr>)],
+) -> Vec<usize> {
+    if batch_verify_proofs(vk, proofs) {
+        return Vec::new();
+    }
+    find_invalid_proofs(vk, proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::pedersen::{random_blinding, ValueCommitment},
+        gadgets::epoch_circuit::{epoch_public_inputs, generate_proof, EpochBalanceCircuit},
+    };
+    use ark_bn254::G1Affine;
+    use ark_crypto_primitives::snark::SNARK;
+
+    #[test]
+    fn test_batch_verification() {
+        let mut rng = OsRng;
+        let (proving_key, verifying_key) =
+            Groth16::<Bn254, LibsnarkReduction>::circuit_specific_setup(
+                EpochBalanceCircuit::blank(),
+                &mut rng,
+            )
+            .unwrap();
+        let mut proofs: Vec<(Proof<Bn254>, Vec<Fr>)> = [(10, 5, 15), (7, -2, 5), (0, 0, 0)]
+            .into_iter()
+            .map(|(initial, delta, last)| {
+                let blinding = random_blinding();
+                let commitment = ValueCommitment::new(i64::from(delta), &blinding);
+                (
+                    generate_proof(
+                        initial,
+                        delta,
+                        last,
+                        commitment,
+                        blinding,
+                        [0; 32],
+                        &proving_key,
+                    ),
+                    epoch_public_inputs(&commitment, &[0; 32]),
+                )
+            })
+            .collect();
+        assert!(batch_verify_proofs(&verifying_key, &proofs));
+
+        // Corrupt the second proof and make sure the fallback points at it
+        proofs[1].0.a = (proofs[1].0.a + G1Affine::generator()).into_affine();
+        assert!(!batch_verify_proofs(&verifying_key, &proofs));
+        assert_eq!(
+            batch_verify_or_find_invalid(&verifying_key, &proofs),
+            vec![1]
+        );
+    }
+}
@@ -28,7 +28,7 @@ impl BlockchainValidatorCircuit {
     }
 }
 impl<F: Field> ConstraintSynthesizer<F> for BlockchainValidatorCircuit {
-    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+    fn generate_constraints(self, _cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
         if !validate_transaction_serial_numbers(self.blockchain_sns, self.spent_serial_numbers) {
             panic!("Could not validate spent transaction serial numbers");
         }
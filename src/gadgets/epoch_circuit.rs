@@ -1,37 +1,118 @@
-use ark_bn254::Bn254;
-use ark_ff::Field;
+use ark_bn254::{Bn254, Fr};
+use ark_ed_on_bn254::{constraints::EdwardsVar, Fr as ScalarField};
+use ark_ff::PrimeField;
 use ark_groth16::{r1cs_to_qap::LibsnarkReduction, Groth16, Proof, ProvingKey};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::CurveVar,
+};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use rand::rngs::OsRng;
-use std::marker::PhantomData;
+
+use crate::{
+    core::pedersen::{value_generator, value_to_scalar, ValueCommitment},
+    gadgets::value_commitment_gadget::{
+        alloc_bits_le, commitment_input, commitment_public_inputs, value_commitment_gadget,
+        VALUE_BITS,
+    },
+};
+
+/// Deltas are signed, so the circuit range checks `delta + DELTA_OFFSET` instead, which puts the delta in
+/// `[-2^31, 2^31)`
+const DELTA_OFFSET: i64 = 1 << (VALUE_BITS - 1);
 
 // Define the circuit for the zk-SNARK
-pub struct EpochBalanceCircuit<F: Field> {
+//
+// Besides the balance check, the circuit proves that the epoch delta is what the public `delta_commitment` (the sum of
+// the organization's committed transaction values) opens to. The other public input is the root of the address set
+// commitment the organization published for the epoch, so the proof only verifies for the addresses it committed to.
+pub struct EpochBalanceCircuit {
     pub initial_balance: i32,
     pub epoch_delta: i32,
     pub final_balance: i32,
-    _marker: PhantomData<F>,
+    pub delta_commitment: ValueCommitment,
+    pub delta_blinding: ScalarField,
+    pub address_set_root: [u8; 32],
 }
 
-impl<F: Field> EpochBalanceCircuit<F> {
-    pub fn new(initial_balance: i32, epoch_delta: i32, final_balance: i32) -> Self {
+impl EpochBalanceCircuit {
+    pub fn new(
+        initial_balance: i32,
+        epoch_delta: i32,
+        final_balance: i32,
+        delta_commitment: ValueCommitment,
+        delta_blinding: ScalarField,
+        address_set_root: [u8; 32],
+    ) -> Self {
         Self {
             initial_balance,
             epoch_delta,
             final_balance,
-            _marker: PhantomData,
+            delta_commitment,
+            delta_blinding,
+            address_set_root,
         }
     }
+    /// A circuit with the right shape for generating the shared setup
+    pub fn blank() -> Self {
+        Self::new(
+            0,
+            0,
+            0,
+            ValueCommitment::zero(),
+            ScalarField::from(0u64),
+            [0; 32],
+        )
+    }
+}
+
+/// The address set root as the circuit takes it
+fn address_set_root_input(address_set_root: &[u8; 32]) -> Fr {
+    Fr::from_le_bytes_mod_order(address_set_root)
+}
+
+/// The public inputs of an epoch proof: the delta commitment followed by the address set root
+pub fn epoch_public_inputs(
+    delta_commitment: &ValueCommitment,
+    address_set_root: &[u8; 32],
+) -> Vec<Fr> {
+    let mut inputs = commitment_public_inputs(delta_commitment);
+    inputs.push(address_set_root_input(address_set_root));
+    inputs
 }
 
-impl<F: Field> ConstraintSynthesizer<F> for EpochBalanceCircuit<F> {
-    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+impl ConstraintSynthesizer<Fr> for EpochBalanceCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
         let sum = self.initial_balance + self.epoch_delta;
         println!("Initial Balance in circuit: {}", self.initial_balance);
         println!("Epoch delta in circuit: {}", self.epoch_delta);
         println!("Final Balance in circuit: {}", self.final_balance);
         assert_eq!(sum, self.final_balance);
-        Ok(())
+
+        let delta_commitment = commitment_input(cs.clone(), &self.delta_commitment)?;
+        // The root takes no part in the balance check, being an input is enough to bind the proof to it
+        let _address_set_root = FpVar::new_input(cs.clone(), || {
+            Ok(address_set_root_input(&self.address_set_root))
+        })?;
+        let shifted_delta = i64::from(self.epoch_delta) + DELTA_OFFSET;
+        let delta_bits = alloc_bits_le(
+            cs.clone(),
+            Ok(value_to_scalar(shifted_delta).into_bigint()),
+            VALUE_BITS,
+            AllocationMode::Witness,
+        )?;
+        let blinding_bits = alloc_bits_le(
+            cs,
+            Ok(self.delta_blinding.into_bigint()),
+            ScalarField::MODULUS_BIT_SIZE as usize,
+            AllocationMode::Witness,
+        )?;
+        // (delta + offset) * G + r * H - offset * G = delta * G + r * H
+        let offset = EdwardsVar::constant(value_generator() * value_to_scalar(DELTA_OFFSET));
+        (value_commitment_gadget(&delta_bits, &blinding_bits)? - offset)
+            .enforce_equal(&delta_commitment)
     }
 }
 // Function to generate zk-SNARK proof
@@ -39,9 +120,19 @@ pub fn generate_proof(
     initial_balance: i32,
     epoch_delta: i32,
     final_balance: i32,
+    delta_commitment: ValueCommitment,
+    delta_blinding: ScalarField,
+    address_set_root: [u8; 32],
     proving_key: &ProvingKey<Bn254>,
 ) -> Proof<Bn254> {
-    let circuit = EpochBalanceCircuit::new(initial_balance, epoch_delta, final_balance);
+    let circuit = EpochBalanceCircuit::new(
+        initial_balance,
+        epoch_delta,
+        final_balance,
+        delta_commitment,
+        delta_blinding,
+        address_set_root,
+    );
 
     // Generate the proof using Groth16 with the LibsnarkReduction
     let rng = &mut OsRng;
@@ -52,3 +143,28 @@ pub fn generate_proof(
     )
     .unwrap()
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pedersen::random_blinding;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_delta_matches_commitment() {
+        let blinding = random_blinding();
+        let commitment = ValueCommitment::new(-25, &blinding);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        EpochBalanceCircuit::new(100, -25, 75, commitment, blinding, [0; 32])
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // A delta that adds up but isn't the committed one is rejected
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        EpochBalanceCircuit::new(100, -20, 80, commitment, blinding, [0; 32])
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,306 @@
+use ark_ed_on_bn254::{constraints::EdwardsVar, Fq, Fr as ScalarField};
+use ark_ff::{One, PrimeField, Zero};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint32::UInt32,
+    uint64::UInt64,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use super::{
+    nullifier_gadget::{note_commitment_gadget, nullifier_gadget, public_key_gadget},
+    value_commitment_gadget::{alloc_bits_le, commitment_public_inputs, value_commitment_gadget},
+};
+use crate::{
+    core::{asset::AssetId, note::Note, pedersen::ValueCommitment},
+    utils::sha256_field_element,
+};
+
+/// Most notes one transaction can spend. The circuit always has this many input slots so a single setup proves every
+/// transaction, the slots a transaction doesn't use are disabled
+pub const JOIN_SPLIT_INPUTS: usize = 2;
+/// Most notes one transaction can create, padded the same way as the inputs
+pub const JOIN_SPLIT_OUTPUTS: usize = 3;
+
+/// Proves a transaction spending `input_notes` into `outputs` is well formed:
+///
+/// - the secret key belongs to the public sender address,
+/// - every input note opens its public commitment and is owned by the sender,
+/// - every public nullifier is correctly derived from its input note and the secret key,
+/// - every public output commitment is a commitment to a note of the public receiver with a 64 bit value,
+/// - every public output value commitment commits to the value of its note,
+/// - every note is of the public asset,
+/// - the input values add up to the output values plus the public fee.
+///
+/// The public inputs are laid out by [`join_split_public_inputs`].
+pub struct JoinSplitCircuit {
+    secret_key: Fq,
+    input_notes: Vec<Note<Fq>>,
+    outputs: Vec<(Note<Fq>, ScalarField)>, // every output note with the blinding of its value commitment
+    fee: u64,
+    asset: AssetId,
+}
+impl JoinSplitCircuit {
+    pub fn new(
+        secret_key: Fq,
+        input_notes: Vec<Note<Fq>>,
+        outputs: Vec<(Note<Fq>, ScalarField)>,
+        fee: u64,
+        asset: AssetId,
+    ) -> Self {
+        assert!(
+            input_notes.len() <= JOIN_SPLIT_INPUTS && outputs.len() <= JOIN_SPLIT_OUTPUTS,
+            "A transaction spends at most {JOIN_SPLIT_INPUTS} notes and creates at most {JOIN_SPLIT_OUTPUTS}"
+        );
+        Self {
+            secret_key,
+            input_notes,
+            outputs,
+            fee,
+            asset,
+        }
+    }
+    /// A circuit with the right shape for generating the setup
+    pub fn blank() -> Self {
+        Self::new(Fq::zero(), vec![], vec![], 0, AssetId::NATIVE)
+    }
+    pub fn public_inputs(&self) -> Vec<Fq> {
+        let inputs: Vec<_> = self
+            .input_notes
+            .iter()
+            .map(|note| (note.commitment(), note.nullifier(&self.secret_key)))
+            .collect();
+        let outputs: Vec<_> = self
+            .outputs
+            .iter()
+            .map(|(note, blinding)| {
+                (
+                    *note.owner_public_key(),
+                    note.commitment(),
+                    value_commitment(note, blinding),
+                )
+            })
+            .collect();
+        join_split_public_inputs(
+            sha256_field_element(&self.secret_key),
+            self.asset,
+            self.fee,
+            &inputs,
+            &outputs,
+        )
+    }
+}
+/// The public inputs of a [`JoinSplitCircuit`]: the sender's public key, the asset and the fee, then for every input
+/// slot whether it is used, the note commitment and the nullifier, then for every output slot whether it is used, the
+/// receiver's public key, the note commitment and the value commitment. Unused slots are all zeros with the zero
+/// value commitment.
+pub fn join_split_public_inputs(
+    sender_public_key: Fq,
+    asset: AssetId,
+    fee: u64,
+    inputs: &[(Fq, Fq)],
+    outputs: &[(Fq, Fq, ValueCommitment)],
+) -> Vec<Fq> {
+    let mut public_inputs = vec![sender_public_key, asset.to_field(), Fq::from(fee)];
+    for slot in 0..JOIN_SPLIT_INPUTS {
+        let (enabled, (commitment, nullifier)) = inputs
+            .get(slot)
+            .map_or((Fq::zero(), (Fq::zero(), Fq::zero())), |input| {
+                (Fq::one(), *input)
+            });
+        public_inputs.extend([enabled, commitment, nullifier]);
+    }
+    for slot in 0..JOIN_SPLIT_OUTPUTS {
+        let (enabled, (owner_public_key, commitment, value_commitment)) = outputs.get(slot).map_or(
+            (
+                Fq::zero(),
+                (Fq::zero(), Fq::zero(), ValueCommitment::zero()),
+            ),
+            |output| (Fq::one(), *output),
+        );
+        public_inputs.extend([enabled, owner_public_key, commitment]);
+        public_inputs.extend(commitment_public_inputs(&value_commitment));
+    }
+    public_inputs
+}
+fn value_commitment(note: &Note<Fq>, blinding: &ScalarField) -> ValueCommitment {
+    ValueCommitment::new(
+        i64::try_from(note.value()).expect("Note value is too large"),
+        blinding,
+    )
+}
+/// What the witness of an unused slot holds
+fn padding_note() -> Note<Fq> {
+    Note::new(Fq::zero(), AssetId::NATIVE, 0, Fq::zero(), Fq::zero())
+}
+/// Pack the bits of a `UInt64` into a field element so values can be added up
+fn uint64_to_fpvar(value: &UInt64<Fq>) -> Result<FpVar<Fq>, SynthesisError> {
+    Boolean::le_bits_to_fp_var(&value.to_bits_le())
+}
+impl ConstraintSynthesizer<Fq> for JoinSplitCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+        let mut public_inputs = self.public_inputs().into_iter();
+        let mut next_input = || {
+            let input = public_inputs.next().expect("Public inputs match the slots");
+            FpVar::new_input(cs.clone(), || Ok(input))
+        };
+        let sender_public_key = next_input()?;
+        let asset_input = next_input()?;
+        let fee = next_input()?;
+        let mut slots = Vec::new();
+        for _ in 0..JOIN_SPLIT_INPUTS {
+            slots.push((next_input()?, next_input()?, next_input()?));
+        }
+        let mut output_slots = Vec::new();
+        for _ in 0..JOIN_SPLIT_OUTPUTS {
+            let slot = (next_input()?, next_input()?, next_input()?);
+            let value_commitment = EdwardsVar::new(next_input()?, next_input()?);
+            output_slots.push((slot, value_commitment));
+        }
+
+        let secret_key = FpVar::new_witness(cs.clone(), || Ok(self.secret_key))?;
+        public_key_gadget(&secret_key)?.enforce_equal(&sender_public_key)?;
+        let asset = UInt32::new_witness(cs.clone(), || Ok(self.asset.id()))?;
+        Boolean::le_bits_to_fp_var(&asset.to_bits_le())?.enforce_equal(&asset_input)?;
+
+        // Unused slots prove nothing about their notes, but can't carry value either
+        let mut input_value = FpVar::zero();
+        for (i, (enabled, commitment, nullifier)) in slots.into_iter().enumerate() {
+            let enabled = enabled.is_eq(&FpVar::one())?;
+            let note = self
+                .input_notes
+                .get(i)
+                .cloned()
+                .unwrap_or_else(padding_note);
+            let value = UInt64::new_witness(cs.clone(), || Ok(note.value()))?;
+            let rho = FpVar::new_witness(cs.clone(), || Ok(*note.rho()))?;
+            let randomness = FpVar::new_witness(cs.clone(), || Ok(*note.randomness()))?;
+            note_commitment_gadget(&sender_public_key, &asset, &value, &rho, &randomness)?
+                .conditional_enforce_equal(&commitment, &enabled)?;
+            nullifier_gadget(&secret_key, &rho)?.conditional_enforce_equal(&nullifier, &enabled)?;
+            let value = uint64_to_fpvar(&value)?;
+            value.conditional_enforce_equal(&FpVar::zero(), &enabled.not())?;
+            input_value += value;
+        }
+
+        let mut output_value = fee;
+        for (j, ((enabled, owner_public_key, commitment), value_commitment)) in
+            output_slots.into_iter().enumerate()
+        {
+            let enabled = enabled.is_eq(&FpVar::one())?;
+            let (note, blinding) = self
+                .outputs
+                .get(j)
+                .cloned()
+                .unwrap_or_else(|| (padding_note(), ScalarField::zero()));
+            let value = UInt64::new_witness(cs.clone(), || Ok(note.value()))?;
+            let rho = FpVar::new_witness(cs.clone(), || Ok(*note.rho()))?;
+            let randomness = FpVar::new_witness(cs.clone(), || Ok(*note.randomness()))?;
+            note_commitment_gadget(&owner_public_key, &asset, &value, &rho, &randomness)?
+                .conditional_enforce_equal(&commitment, &enabled)?;
+            let blinding_bits = alloc_bits_le(
+                cs.clone(),
+                Ok(blinding.into_bigint()),
+                ScalarField::MODULUS_BIT_SIZE as usize,
+                AllocationMode::Witness,
+            )?;
+            value_commitment_gadget(&value.to_bits_le(), &blinding_bits)?
+                .conditional_enforce_equal(&value_commitment, &enabled)?;
+            let value = uint64_to_fpvar(&value)?;
+            value.conditional_enforce_equal(&FpVar::zero(), &enabled.not())?;
+            output_value += value;
+        }
+
+        // A handful of 64 bit values can't wrap around the field, so this is a plain integer equality
+        input_value.enforce_equal(&output_value)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{address::Address, pedersen::random_blinding};
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::thread_rng;
+
+    fn is_satisfied(circuit: JoinSplitCircuit) -> bool {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_join_split() {
+        let sender = Address::new(Fq::from(1u64));
+        let first_receiver = Address::new(Fq::from(2u64));
+        let second_receiver = Address::new(Fq::from(3u64));
+        let mut rng = thread_rng();
+        let asset = AssetId::new(2);
+        let inputs = vec![
+            Note::random(*sender.public_key(), asset, 40, &mut rng),
+            Note::random(*sender.public_key(), asset, 25, &mut rng),
+        ];
+        let mut output = |receiver: &Address<Fq>, asset: AssetId, value: u64| {
+            (
+                Note::random(*receiver.public_key(), asset, value, &mut rng),
+                random_blinding(),
+            )
+        };
+        let outputs = vec![
+            output(&first_receiver, asset, 30),
+            output(&second_receiver, asset, 20),
+            output(&sender, asset, 10),
+        ];
+
+        let circuit =
+            JoinSplitCircuit::new(*sender.secret_key(), inputs.clone(), outputs, 5, asset);
+        let public_inputs = circuit.public_inputs();
+        assert!(public_inputs.starts_with(&[*sender.public_key(), Fq::from(2u64), Fq::from(5u64)]));
+        assert_eq!(
+            public_inputs.len(),
+            JoinSplitCircuit::blank().public_inputs().len()
+        );
+        assert!(is_satisfied(circuit));
+
+        // Slots a transaction doesn't use are padded
+        let single = vec![output(&first_receiver, asset, 40)];
+        assert!(is_satisfied(JoinSplitCircuit::new(
+            *sender.secret_key(),
+            vec![inputs[0].clone()],
+            single.clone(),
+            0,
+            asset
+        )));
+
+        // Only the owner of the notes can spend them
+        assert!(!is_satisfied(JoinSplitCircuit::new(
+            *first_receiver.secret_key(),
+            vec![inputs[0].clone()],
+            single,
+            0,
+            asset
+        )));
+
+        // Outputs worth more than the inputs don't satisfy the circuit
+        let outputs = vec![output(&second_receiver, asset, 70)];
+        assert!(!is_satisfied(JoinSplitCircuit::new(
+            *sender.secret_key(),
+            inputs.clone(),
+            outputs,
+            0,
+            asset
+        )));
+
+        // Neither do outputs of another asset
+        let outputs = vec![output(&second_receiver, AssetId::NATIVE, 65)];
+        assert!(!is_satisfied(JoinSplitCircuit::new(
+            *sender.secret_key(),
+            inputs,
+            outputs,
+            0,
+            asset
+        )));
+    }
+}
@@ -1,6 +1,4 @@
 use ark_ff::{BigInteger, PrimeField};
-use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar, ToBytesGadget};
-use ark_relations::r1cs::ConstraintSystemRef;
 use rs_merkle::MerkleProof;
 use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
 pub struct MerkleTreeGadget;
@@ -22,27 +20,19 @@ impl MerkleTreeGadget {
         // Convert the root hash to the PrimeField element
         F::from_be_bytes_mod_order(&tree.root().unwrap())
     }
-    pub fn create_root_hash<F: PrimeField>(
-        leaves: Vec<FpVar<F>>,
-        cs: ConstraintSystemRef<F>,
-    ) -> FpVar<F> {
+    pub fn create_root_hash<F: PrimeField>(leaves: Vec<F>) -> F {
         // Pre-allocate memory for the leaf hashes vector
         let mut leaf_hashes: Vec<[u8; 32]> = Vec::with_capacity(leaves.len());
 
         for leaf in leaves.iter() {
-            let bytes = leaf.to_bytes().unwrap();
+            let bytes = leaf.into_bigint().to_bytes_le();
             let mut hash_input = [0u8; 32];
 
             // Directly copy bytes into the hash_input array, avoiding intermediate Vec<u8>
-            for (i, byte) in bytes.iter().enumerate() {
-                hash_input[i] = byte.value().unwrap();
-            }
+            hash_input[..bytes.len()].copy_from_slice(&bytes);
 
             // Compute the hash and push it to the pre-allocated vector
-            let hash = Sha256::hash(&hash_input);
-            let mut hash_array = [0u8; 32];
-            hash_array.copy_from_slice(&hash);
-            leaf_hashes.push(hash_array);
+            leaf_hashes.push(Sha256::hash(&hash_input));
         }
 
         // Create the Merkle tree from leaf hashes
@@ -52,32 +42,21 @@ impl MerkleTreeGadget {
         let root_hash = tree.root().unwrap();
 
         // Convert the root hash to a field element
-        let root_hash_field = F::from_le_bytes_mod_order(&root_hash);
-
-        // Convert the root hash field element to an FpVar
-        FpVar::new_witness(cs, || Ok(root_hash_field)).unwrap()
+        F::from_le_bytes_mod_order(&root_hash)
     }
-    pub fn create_merkle_tree<F: PrimeField>(
-        leaves: Vec<FpVar<F>>,
-        cs: ConstraintSystemRef<F>,
-    ) -> MerkleTree<Sha256> {
+    pub fn create_merkle_tree<F: PrimeField>(leaves: Vec<F>) -> MerkleTree<Sha256> {
         // Pre-allocate memory for the leaf hashes
         let mut leaf_hashes: Vec<[u8; 32]> = Vec::with_capacity(leaves.len());
 
         for leaf in leaves.iter() {
-            let bytes = leaf.to_bytes().unwrap();
+            let bytes = leaf.into_bigint().to_bytes_le();
             let mut hash_input = [0u8; 32];
 
             // Directly copy the byte values to the hash input array
-            for (i, byte) in bytes.iter().enumerate() {
-                hash_input[i] = byte.value().unwrap();
-            }
+            hash_input[..bytes.len()].copy_from_slice(&bytes);
 
             // Compute the hash and push it to the pre-allocated vector
-            let hash = Sha256::hash(&hash_input);
-            let mut hash_array = [0u8; 32];
-            hash_array.copy_from_slice(&hash);
-            leaf_hashes.push(hash_array);
+            leaf_hashes.push(Sha256::hash(&hash_input));
         }
 
         // Create the Merkle tree from leaf hashes
@@ -85,7 +64,7 @@ impl MerkleTreeGadget {
     }
 
     pub fn generate_proof_and_validate<F: PrimeField>(
-        leaves: &[FpVar<F>],
+        leaves: &[F],
         indices_to_prove: Vec<usize>,
     ) -> bool {
         // Pre-allocate memory for the leaf hashes
@@ -93,18 +72,10 @@ impl MerkleTreeGadget {
 
         // Compute leaf hashes once and store them for both tree creation and proof validation
         for leaf in leaves.iter() {
-            let bytes = leaf.to_bytes().unwrap();
-
-            // Collect the bytes directly into the hash and compute the hash
-            let hash = Sha256::hash(
-                &bytes
-                    .iter()
-                    .map(|byte| byte.value().unwrap())
-                    .collect::<Vec<u8>>(),
-            );
+            let bytes = leaf.into_bigint().to_bytes_le();
 
             // Push the hash into the pre-allocated vector
-            leaf_hashes.push(hash);
+            leaf_hashes.push(Sha256::hash(&bytes));
         }
         // Create the Merkle tree from the pre-computed leaf hashes
         let tree = MerkleTree::<Sha256>::from_leaves(&leaf_hashes);
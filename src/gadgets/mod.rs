@@ -1,4 +1,10 @@
+pub mod aggregation;
 pub mod asset_proof;
+pub mod batch_verifier;
 pub mod blockchain_validator;
 pub mod epoch_circuit;
+pub mod join_split;
 pub mod merkle_gadget;
+pub mod nullifier_gadget;
+pub mod signature_gadget;
+pub mod value_commitment_gadget;
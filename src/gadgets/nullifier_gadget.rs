@@ -0,0 +1,124 @@
+use ark_crypto_primitives::crh::{
+    sha256::constraints::{Sha256Gadget, UnitVar},
+    CRHSchemeGadget,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, uint32::UInt32, uint64::UInt64, uint8::UInt8,
+    ToBytesGadget, ToConstraintFieldGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::core::{
+    note::{Note, NOTE_COMMITMENT_TAG},
+    serial_number::NULLIFIER_PRF_TAG,
+};
+
+/// Hash bytes with SHA-256 in the circuit and pack the digest into a field element
+fn sha256_to_field<F: PrimeField>(input: &[UInt8<F>]) -> Result<FpVar<F>, SynthesisError> {
+    Ok(Sha256Gadget::evaluate(&UnitVar::default(), input)?
+        .0
+        .to_constraint_field()?
+        .remove(0))
+}
+/// In-circuit address derivation, `pk = SHA256(sk)`
+pub fn public_key_gadget<F: PrimeField>(secret_key: &FpVar<F>) -> Result<FpVar<F>, SynthesisError> {
+    sha256_to_field(&secret_key.to_bytes()?)
+}
+/// In-circuit nullifier derivation, `sn = SHA256(tag || sk || ρ)`
+pub fn nullifier_gadget<F: PrimeField>(
+    secret_key: &FpVar<F>,
+    rho: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut input = vec![UInt8::constant(NULLIFIER_PRF_TAG)];
+    input.extend(secret_key.to_bytes()?);
+    input.extend(rho.to_bytes()?);
+    sha256_to_field(&input)
+}
+
+/// In-circuit note commitment, `cm = SHA256(tag || owner_pk || asset || value || ρ || r)`
+pub fn note_commitment_gadget<F: PrimeField>(
+    owner_public_key: &FpVar<F>,
+    asset: &UInt32<F>,
+    value: &UInt64<F>,
+    rho: &FpVar<F>,
+    randomness: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut input = vec![UInt8::constant(NOTE_COMMITMENT_TAG)];
+    input.extend(owner_public_key.to_bytes()?);
+    input.extend(asset.to_bytes()?);
+    input.extend(value.to_bytes()?);
+    input.extend(rho.to_bytes()?);
+    input.extend(randomness.to_bytes()?);
+    sha256_to_field(&input)
+}
+
+/// Proves that `nullifier` belongs to the note behind `note_commitment` and was derived by the holder of the secret key
+/// that owns the note, without revealing the note or the key
+pub struct NullifierCircuit<F: PrimeField> {
+    pub note_commitment: F,
+    pub nullifier: F,
+    pub secret_key: F,
+    pub note: Note<F>,
+}
+impl<F: PrimeField> ConstraintSynthesizer<F> for NullifierCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let note_commitment = FpVar::new_input(cs.clone(), || Ok(self.note_commitment))?;
+        let nullifier = FpVar::new_input(cs.clone(), || Ok(self.nullifier))?;
+        let secret_key = FpVar::new_witness(cs.clone(), || Ok(self.secret_key))?;
+        let asset = UInt32::new_witness(cs.clone(), || Ok(self.note.asset().id()))?;
+        let value = UInt64::new_witness(cs.clone(), || Ok(self.note.value()))?;
+        let rho = FpVar::new_witness(cs.clone(), || Ok(*self.note.rho()))?;
+        let randomness = FpVar::new_witness(cs, || Ok(*self.note.randomness()))?;
+
+        // The owner of the note is whoever knows the secret key behind its public key
+        let owner_public_key = public_key_gadget(&secret_key)?;
+        note_commitment_gadget(&owner_public_key, &asset, &value, &rho, &randomness)?
+            .enforce_equal(&note_commitment)?;
+        nullifier_gadget(&secret_key, &rho)?.enforce_equal(&nullifier)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{address::Address, asset::AssetId};
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_nullifier_derivation() {
+        let address = Address::new(Fr::from(7u64));
+        let note = Note::new(
+            *address.public_key(),
+            AssetId::NATIVE,
+            25,
+            Fr::from(3u64),
+            Fr::from(4u64),
+        );
+        let nullifier = note.nullifier(address.secret_key());
+        assert_ne!(nullifier, note.nullifier(&Fr::from(8u64)));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        NullifierCircuit {
+            note_commitment: note.commitment(),
+            nullifier,
+            secret_key: *address.secret_key(),
+            note: note.clone(),
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // Someone who knows the note but not the secret key can't produce the nullifier
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        NullifierCircuit {
+            note_commitment: note.commitment(),
+            nullifier,
+            secret_key: Fr::from(8u64),
+            note,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
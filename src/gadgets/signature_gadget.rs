@@ -0,0 +1,124 @@
+use std::borrow::Borrow;
+
+use ark_ec::AffineRepr;
+use ark_ed_on_bn254::{constraints::EdwardsVar, EdwardsAffine, Fq};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::CurveVar,
+    ToBitsGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, Namespace, SynthesisError};
+
+use crate::{
+    core::signature::{Signature, SIGNATURE_POSEIDON_CONFIG},
+    gadgets::value_commitment_gadget::alloc_bits_le,
+    utils::poseidon_hash_var,
+};
+
+/// A Schnorr signature allocated in the constraint system. `s` is kept as its little endian bits since it is only
+/// ever used as a scalar
+pub struct SignatureVar {
+    pub r: EdwardsVar,
+    pub s_bits: Vec<Boolean<Fq>>,
+}
+impl AllocVar<Signature, Fq> for SignatureVar {
+    fn new_variable<T: Borrow<Signature>>(
+        cs: impl Into<Namespace<Fq>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let signature = f().map(|signature| *signature.borrow());
+        let r = EdwardsVar::new_variable(
+            cs.clone(),
+            || signature.map(|signature| *signature.r()),
+            mode,
+        )?;
+        let s_bits = alloc_bits_le(
+            cs,
+            signature.map(|signature| signature.s().into_bigint()),
+            ark_ed_on_bn254::Fr::MODULUS_BIT_SIZE as usize,
+            mode,
+        )?;
+        Ok(Self { r, s_bits })
+    }
+}
+
+/// Check `s * G = R + Poseidon(R, vk, m) * vk` in the circuit, the counterpart of [`crate::core::signature::verify`]
+pub fn verify_signature_gadget(
+    cs: ConstraintSystemRef<Fq>,
+    verification_key: &EdwardsVar,
+    message: &FpVar<Fq>,
+    signature: &SignatureVar,
+) -> Result<Boolean<Fq>, SynthesisError> {
+    let challenge = poseidon_hash_var(
+        cs,
+        &SIGNATURE_POSEIDON_CONFIG,
+        &[
+            signature.r.x.clone(),
+            signature.r.y.clone(),
+            verification_key.x.clone(),
+            verification_key.y.clone(),
+            message.clone(),
+        ],
+    )?;
+    let lhs = EdwardsVar::constant(EdwardsAffine::generator().into_group())
+        .scalar_mul_le(signature.s_bits.iter())?;
+    let rhs = verification_key.scalar_mul_le(challenge.to_bits_le()?.iter())? + &signature.r;
+    lhs.is_eq(&rhs)
+}
+
+/// Proves knowledge of a valid signature on a public message under a public verification key
+pub struct SignatureVerificationCircuit {
+    pub verification_key: EdwardsAffine,
+    pub message: Fq,
+    pub signature: Signature,
+}
+impl ConstraintSynthesizer<Fq> for SignatureVerificationCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+        let verification_key = EdwardsVar::new_input(cs.clone(), || Ok(self.verification_key))?;
+        let message = FpVar::new_input(cs.clone(), || Ok(self.message))?;
+        let signature = SignatureVar::new_witness(cs.clone(), || Ok(self.signature))?;
+        verify_signature_gadget(cs, &verification_key, &message, &signature)?
+            .enforce_equal(&Boolean::TRUE)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::signature::{sign, verification_key};
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_signature_gadget() {
+        let secret_key = Fr::from(1234u64);
+        let message = Fq::from(99u64);
+        let signature = sign(&secret_key, message);
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        SignatureVerificationCircuit {
+            verification_key: verification_key(&secret_key),
+            message,
+            signature,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        SignatureVerificationCircuit {
+            verification_key: verification_key(&secret_key),
+            message: Fq::from(100u64),
+            signature,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,128 @@
+use ark_ed_on_bn254::{constraints::EdwardsVar, Fq};
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::CurveVar,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::core::pedersen::{blinding_generator, value_generator, ValueCommitment};
+
+/// Committed transaction values have to fit in this many bits
+pub const VALUE_BITS: usize = 32;
+/// Transaction outputs are one bit shorter, so every output value fits a signed balance
+pub const OUTPUT_VALUE_BITS: usize = VALUE_BITS - 1;
+
+/// Allocate the `num_bits` little endian bits of `value`. Allocating exactly `num_bits` booleans is the range proof:
+/// whatever the prover does, the bits can only encode a number in `[0, 2^num_bits)`
+pub fn alloc_bits_le<F: PrimeField, B: BigInteger>(
+    cs: ConstraintSystemRef<F>,
+    value: Result<B, SynthesisError>,
+    num_bits: usize,
+    mode: AllocationMode,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let bits = value.map(|value| value.to_bits_le());
+    (0..num_bits)
+        .map(|i| {
+            Boolean::new_variable(
+                cs.clone(),
+                || bits.as_ref().map(|bits| bits[i]).map_err(|e| *e),
+                mode,
+            )
+        })
+        .collect()
+}
+/// Allocate a public commitment as its two coordinates, `x` first
+pub fn commitment_input(
+    cs: ConstraintSystemRef<Fq>,
+    commitment: &ValueCommitment,
+) -> Result<EdwardsVar, SynthesisError> {
+    Ok(EdwardsVar::new(
+        FpVar::new_input(cs.clone(), || Ok(commitment.point().x))?,
+        FpVar::new_input(cs, || Ok(commitment.point().y))?,
+    ))
+}
+/// The public inputs a circuit built with [`commitment_input`] expects for `commitment`
+pub fn commitment_public_inputs(commitment: &ValueCommitment) -> Vec<Fq> {
+    vec![commitment.point().x, commitment.point().y]
+}
+/// `value * G + blinding * H` from the bits of the value and of the blinding factor
+pub fn value_commitment_gadget(
+    value_bits: &[Boolean<Fq>],
+    blinding_bits: &[Boolean<Fq>],
+) -> Result<EdwardsVar, SynthesisError> {
+    let value_part =
+        EdwardsVar::constant(value_generator().into()).scalar_mul_le(value_bits.iter())?;
+    let blinding_part =
+        EdwardsVar::constant(blinding_generator().into()).scalar_mul_le(blinding_bits.iter())?;
+    Ok(value_part + blinding_part)
+}
+
+/// Range proof for a committed transaction output: proves `commitment` opens to a value in `[0, 2^OUTPUT_VALUE_BITS)`
+pub struct ValueRangeCircuit {
+    pub commitment: ValueCommitment,
+    pub value: u64,
+    pub blinding: ark_ed_on_bn254::Fr,
+}
+impl ValueRangeCircuit {
+    /// A circuit with the right shape for generating the setup
+    pub fn blank() -> Self {
+        Self {
+            commitment: ValueCommitment::zero(),
+            value: 0,
+            blinding: ark_ed_on_bn254::Fr::from(0u64),
+        }
+    }
+}
+impl ConstraintSynthesizer<Fq> for ValueRangeCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+        let commitment = commitment_input(cs.clone(), &self.commitment)?;
+        let value_bits = alloc_bits_le(
+            cs.clone(),
+            Ok(Fq::from(self.value).into_bigint()),
+            OUTPUT_VALUE_BITS,
+            AllocationMode::Witness,
+        )?;
+        let blinding_bits = alloc_bits_le(
+            cs,
+            Ok(self.blinding.into_bigint()),
+            ark_ed_on_bn254::Fr::MODULUS_BIT_SIZE as usize,
+            AllocationMode::Witness,
+        )?;
+        value_commitment_gadget(&value_bits, &blinding_bits)?.enforce_equal(&commitment)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pedersen::random_blinding;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_value_range_proof() {
+        let blinding = random_blinding();
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        ValueRangeCircuit {
+            commitment: ValueCommitment::new(1_000, &blinding),
+            value: 1_000,
+            blinding,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // 2^31 does not fit in the allocated bits, so the commitment can't be opened
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        ValueRangeCircuit {
+            commitment: ValueCommitment::new(1 << OUTPUT_VALUE_BITS, &blinding),
+            value: 1 << OUTPUT_VALUE_BITS,
+            blinding,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -1,9 +1,20 @@
 use std::collections::VecDeque;
 
 use ark_bn254::Fr;
-use ark_ff::{BigInteger, PrimeField};
+use ark_crypto_primitives::{
+    crh::{sha256::Sha256, CRHScheme},
+    sponge::{
+        constraints::CryptographicSpongeVar,
+        poseidon::{
+            constraints::PoseidonSpongeVar, find_poseidon_ark_and_mds, PoseidonConfig,
+            PoseidonSponge,
+        },
+        Absorb, CryptographicSponge,
+    },
+};
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
 use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
-use ark_relations::r1cs::ConstraintSystemRef;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
 use rand::Rng;
 
 // Helper function to generate a random value in the specified range
@@ -15,6 +26,42 @@ pub fn generate_random_in_range() -> Fr {
     #[allow(clippy::let_and_return)]
     ret
 }
+/// Hash a field element with SHA-256 and pack the digest back into a field element.
+///
+/// This is the native counterpart of hashing `FpVar::to_bytes` with `Sha256Gadget` and calling `to_constraint_field`
+/// on the digest, so values computed here match what a circuit computes for the same input.
+pub fn sha256_field_element<F: PrimeField>(input: &F) -> F {
+    sha256_bytes_to_field(input.into_bigint().to_bytes_le())
+}
+/// Hash raw bytes with SHA-256 and pack the digest into a field element
+pub fn sha256_bytes_to_field<F: PrimeField>(input: Vec<u8>) -> F {
+    let digest = Sha256::evaluate(&(), input).unwrap();
+    ToConstraintField::<F>::to_field_elements(digest.as_slice()).unwrap()[0]
+}
+/// Poseidon parameters for a width 3 (rate 2, capacity 1) sponge with the x^5 S-box, 8 full rounds and
+/// 57 partial rounds. The round constants and MDS matrix come from the Grain LFSR, so they only depend on `F`.
+///
+/// Generating them isn't free, callers that hash a lot should build the config once and keep it around.
+pub fn poseidon_config<F: PrimeField>() -> PoseidonConfig<F> {
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, 2, 8, 57, 0);
+    PoseidonConfig::new(8, 57, 5, mds, ark, 2, 1)
+}
+/// Hash field elements with Poseidon and squeeze out a single field element
+pub fn poseidon_hash<F: PrimeField + Absorb>(config: &PoseidonConfig<F>, inputs: &[F]) -> F {
+    let mut sponge = PoseidonSponge::new(config);
+    sponge.absorb(&inputs);
+    sponge.squeeze_field_elements(1)[0]
+}
+/// In-circuit counterpart of [`poseidon_hash`]
+pub fn poseidon_hash_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    config: &PoseidonConfig<F>,
+    inputs: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::new(cs, config);
+    sponge.absorb(&inputs)?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
 /// Convert a string to a field element `F`.
 fn string_to_field<F: PrimeField>(s: &str) -> F {
     let mut bytes = s.as_bytes().to_vec();
@@ -0,0 +1,441 @@
+//! Verification that only sees what organizations publish. Nothing in here has access to an [`Organization`]'s
+//! balances, blindings or serial numbers.
+//!
+//! [`Organization`]: crate::core::org::Organization
+use std::collections::{BTreeMap, HashMap};
+
+use ark_bn254::Bn254;
+use ark_ff::PrimeField;
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::{
+    core::{
+        asset::AssetId,
+        blockchain::Blockchain,
+        bulletin_board::BulletinBoard,
+        disjointness::AddressSetCommitment,
+        external::ExternalFlow,
+        membership::{members, MembershipRecord},
+        pedersen::ValueCommitment,
+    },
+    gadgets::{batch_verifier::batch_verify_or_find_invalid, epoch_circuit::epoch_public_inputs},
+};
+
+/// The public record of an epoch an audit is checked against: who took part, the delta commitment each of them
+/// published for each asset, the root of the address set each of them committed to, and the external flows
+#[derive(Clone, Debug)]
+pub struct EpochStatement {
+    epoch: usize,
+    members: Vec<String>,
+    delta_commitments: HashMap<(String, AssetId), ValueCommitment>,
+    address_set_roots: HashMap<String, [u8; 32]>,
+    external_flows: HashMap<AssetId, ExternalFlow>,
+}
+impl EpochStatement {
+    pub fn new(
+        epoch: usize,
+        members: Vec<String>,
+        delta_commitments: HashMap<(String, AssetId), ValueCommitment>,
+        address_set_roots: HashMap<String, [u8; 32]>,
+        external_flows: HashMap<AssetId, ExternalFlow>,
+    ) -> Self {
+        Self {
+            epoch,
+            members,
+            delta_commitments,
+            address_set_roots,
+            external_flows,
+        }
+    }
+    /// Read the statement of an epoch off the blockchain. Only the commitments of the organizations that were members
+    /// during the epoch count
+    pub fn from_blockchain<F: PrimeField>(blockchain: &Blockchain<F>, epoch: usize) -> Self {
+        let members = blockchain.members(epoch);
+        let delta_commitments = blockchain
+            .delta_commitments(epoch)
+            .into_iter()
+            .filter(|((identifier, _), _)| members.contains(identifier))
+            .collect();
+        let address_set_roots = address_set_roots(blockchain.address_set_commitments(epoch));
+        Self::new(
+            epoch,
+            members,
+            delta_commitments,
+            address_set_roots,
+            blockchain.external_flows(epoch),
+        )
+    }
+    /// Read the statement of an epoch off the bulletin board, with the members the `membership` records give for it
+    pub fn from_bulletin_board<F: PrimeField>(
+        board: &BulletinBoard<F>,
+        membership: &[MembershipRecord],
+        epoch: usize,
+    ) -> Self {
+        let members = members(membership, epoch);
+        let delta_commitments = board
+            .delta_commitments(epoch)
+            .into_iter()
+            .filter(|((identifier, _), _)| members.contains(identifier))
+            .collect();
+        let address_set_roots = address_set_roots(board.address_set_commitments(epoch));
+        Self::new(
+            epoch,
+            members,
+            delta_commitments,
+            address_set_roots,
+            board.external_flows(epoch),
+        )
+    }
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+    pub fn delta_commitment(&self, identifier: &str, asset: AssetId) -> Option<ValueCommitment> {
+        self.delta_commitments
+            .get(&(identifier.to_owned(), asset))
+            .copied()
+    }
+    /// The root of the address set the organization committed to, which its epoch proofs are bound to
+    pub fn address_set_root(&self, identifier: &str) -> Option<&[u8; 32]> {
+        self.address_set_roots.get(identifier)
+    }
+    pub fn address_set_roots(&self) -> &HashMap<String, [u8; 32]> {
+        &self.address_set_roots
+    }
+    /// The assets an organization published a delta commitment for
+    pub fn published_assets(&self, identifier: &str) -> Vec<AssetId> {
+        let mut assets: Vec<AssetId> = self
+            .delta_commitments
+            .keys()
+            .filter(|(owner, _)| owner == identifier)
+            .map(|(_, asset)| *asset)
+            .collect();
+        assets.sort_unstable();
+        assets
+    }
+    /// Whether the delta commitments and external flows of every asset add up to a commitment to zero, and every
+    /// external flow opens to its public amounts
+    pub fn is_zero_sum(&self) -> bool {
+        let mut sums: HashMap<AssetId, ValueCommitment> = HashMap::new();
+        for ((_, asset), commitment) in &self.delta_commitments {
+            let sum = sums.entry(*asset).or_default();
+            *sum = *sum + *commitment;
+        }
+        for (asset, flow) in &self.external_flows {
+            let sum = sums.entry(*asset).or_default();
+            *sum = *sum + flow.commitment();
+        }
+        self.external_flows
+            .values()
+            .all(ExternalFlow::is_consistent)
+            && sums.into_values().all(|sum| sum == ValueCommitment::zero())
+    }
+}
+
+fn address_set_roots(
+    commitments: Vec<(String, AddressSetCommitment)>,
+) -> HashMap<String, [u8; 32]> {
+    commitments
+        .into_iter()
+        .map(|(identifier, commitment)| (identifier, *commitment.root()))
+        .collect()
+}
+
+/// The proofs an organization hands the auditor for one asset
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProofSubmission {
+    identifier: String,
+    asset: AssetId,
+    epoch_proof: Proof<Bn254>,
+    asset_proof: Proof<Bn254>,
+}
+impl ProofSubmission {
+    pub fn new(
+        identifier: String,
+        asset: AssetId,
+        epoch_proof: Proof<Bn254>,
+        asset_proof: Proof<Bn254>,
+    ) -> Self {
+        Self {
+            identifier,
+            asset,
+            epoch_proof,
+            asset_proof,
+        }
+    }
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn asset(&self) -> AssetId {
+        self.asset
+    }
+    pub fn epoch_proof(&self) -> &Proof<Bn254> {
+        &self.epoch_proof
+    }
+    pub fn asset_proof(&self) -> &Proof<Bn254> {
+        &self.asset_proof
+    }
+}
+
+/// Why an organization failed its audit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditFailure {
+    /// The organization published no delta commitment at all
+    NothingPublished,
+    /// The organization published a delta commitment for the asset but submitted no proofs for it
+    MissingProofs(AssetId),
+    /// The organization submitted proofs for an asset it published no delta commitment for
+    Unpublished(AssetId),
+    /// The organization published no address set commitment to check its epoch proofs against
+    NoAddressSet,
+    /// The epoch proof doesn't verify against the published delta commitment and address set
+    InvalidEpochProof(AssetId),
+    /// The asset proof doesn't verify
+    InvalidAssetProof(AssetId),
+}
+
+/// The outcome of one organization's audit for one epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verdict {
+    identifier: String,
+    epoch: usize,
+    failures: Vec<AuditFailure>,
+}
+impl Verdict {
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+    pub fn failures(&self) -> &[AuditFailure] {
+        &self.failures
+    }
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Checks organizations' proofs against the public record, holding nothing but the circuits' verifying keys
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Auditor {
+    epoch_verifying_key: VerifyingKey<Bn254>,
+    asset_verifying_key: VerifyingKey<Bn254>,
+}
+impl Auditor {
+    pub fn new(
+        epoch_verifying_key: VerifyingKey<Bn254>,
+        asset_verifying_key: VerifyingKey<Bn254>,
+    ) -> Self {
+        Self {
+            epoch_verifying_key,
+            asset_verifying_key,
+        }
+    }
+    /// Give a verdict for every member of the epoch, in the statement's order. Epoch proofs are checked against the
+    /// delta commitments and address set roots the statement records, not anything the organization claims in its
+    /// submission.
+    pub fn audit(
+        &self,
+        statement: &EpochStatement,
+        submissions: &[ProofSubmission],
+    ) -> Vec<Verdict> {
+        let mut failures: BTreeMap<&str, Vec<AuditFailure>> = BTreeMap::new();
+        let mut epoch_proofs = Vec::new();
+        let mut asset_proofs = Vec::new();
+        let mut audited = Vec::new();
+        for member in statement.members() {
+            let member_failures = failures.entry(member).or_default();
+            let published = statement.published_assets(member);
+            if published.is_empty() {
+                member_failures.push(AuditFailure::NothingPublished);
+            }
+            let address_set_root = statement.address_set_root(member);
+            if address_set_root.is_none() {
+                member_failures.push(AuditFailure::NoAddressSet);
+            }
+            let submitted: Vec<&ProofSubmission> = submissions
+                .iter()
+                .filter(|submission| submission.identifier == *member)
+                .collect();
+            for asset in &published {
+                if !submitted
+                    .iter()
+                    .any(|submission| submission.asset == *asset)
+                {
+                    member_failures.push(AuditFailure::MissingProofs(*asset));
+                }
+            }
+            for submission in submitted {
+                let Some(commitment) = statement.delta_commitment(member, submission.asset) else {
+                    member_failures.push(AuditFailure::Unpublished(submission.asset));
+                    continue;
+                };
+                let Some(address_set_root) = address_set_root else {
+                    continue;
+                };
+                epoch_proofs.push((
+                    submission.epoch_proof.clone(),
+                    epoch_public_inputs(&commitment, address_set_root),
+                ));
+                // The asset circuit takes no public inputs
+                asset_proofs.push((submission.asset_proof.clone(), vec![]));
+                audited.push((member.as_str(), submission.asset));
+            }
+        }
+
+        for index in batch_verify_or_find_invalid(&self.epoch_verifying_key, &epoch_proofs) {
+            let (member, asset) = audited[index];
+            failures
+                .entry(member)
+                .or_default()
+                .push(AuditFailure::InvalidEpochProof(asset));
+        }
+        for index in batch_verify_or_find_invalid(&self.asset_verifying_key, &asset_proofs) {
+            let (member, asset) = audited[index];
+            failures
+                .entry(member)
+                .or_default()
+                .push(AuditFailure::InvalidAssetProof(asset));
+        }
+        statement
+            .members()
+            .iter()
+            .map(|member| Verdict {
+                identifier: member.clone(),
+                epoch: statement.epoch(),
+                failures: failures.remove(member.as_str()).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        address::Address,
+        network::fixtures::{forward, two_organization_network},
+        pedersen::random_blinding,
+        transaction::Transaction,
+    };
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_audit() {
+        let (mut network, range_key, first_addresses, second_addresses) =
+            two_organization_network(1);
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(1u64),
+                30,
+                first_addresses[0].clone(),
+                second_addresses[0].public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        // Value leaving to an external wallet only adds up against the external flow
+        let wallet = Address::new(Fr::from(7u64));
+        network.register_external_wallet(wallet.public_address());
+        forward(
+            &mut network,
+            Transaction::new(
+                Fr::from(2u64),
+                5,
+                second_addresses[0].clone(),
+                wallet.public_address(),
+                Fr::from(0u64),
+                &range_key,
+            ),
+        );
+        network.transfer_delta_to_organization_balance();
+        network.publish_delta_commitments();
+
+        // The auditor only gets the verifying keys, the proofs and what's on the blockchain
+        let (auditor, submissions) = network.prepare_audit();
+        let statement = EpochStatement::from_blockchain(&network.blockchain(), 0);
+        assert!(statement.is_zero_sum());
+        let verdicts = auditor.audit(&statement, &submissions);
+        assert_eq!(verdicts.len(), 2);
+        assert!(verdicts
+            .iter()
+            .all(|verdict| verdict.is_valid() && verdict.epoch() == 0));
+
+        // The same audit passes reading everything off the bulletin board, which needs the posted external flows to add
+        // up
+        network.post_epoch_artifacts(&submissions).unwrap();
+        let board = network.bulletin_board();
+        let blockchain = network.blockchain();
+        assert!(board.verify_chain(blockchain.membership_history()));
+        let posted = EpochStatement::from_bulletin_board(board, blockchain.membership_history(), 0);
+        assert_eq!(posted.members(), statement.members());
+        assert!(posted.is_zero_sum());
+        assert!(!EpochStatement::new(
+            0,
+            statement.members().to_vec(),
+            board.delta_commitments(0),
+            posted.address_set_roots().clone(),
+            HashMap::new(),
+        )
+        .is_zero_sum());
+        assert!(auditor
+            .audit(&posted, &board.submissions(0))
+            .iter()
+            .all(Verdict::is_valid));
+
+        // Missing proofs and proofs that don't match the published commitment fail the organization they belong to
+        let verdicts = auditor.audit(&statement, &submissions[1..]);
+        assert_eq!(
+            verdicts
+                .iter()
+                .filter(|verdict| !verdict.is_valid())
+                .map(|verdict| (verdict.identifier(), verdict.failures().to_vec()))
+                .collect::<Vec<_>>(),
+            vec![(
+                submissions[0].identifier(),
+                vec![AuditFailure::MissingProofs(AssetId::NATIVE)]
+            )]
+        );
+        let mut delta_commitments = network.blockchain().delta_commitments(0);
+        delta_commitments.insert(
+            ("org2".to_owned(), AssetId::NATIVE),
+            ValueCommitment::new(40, &random_blinding()),
+        );
+        let forged = EpochStatement::new(
+            0,
+            statement.members().to_vec(),
+            delta_commitments,
+            statement.address_set_roots().clone(),
+            network.blockchain().external_flows(0),
+        );
+        assert!(!forged.is_zero_sum());
+        let verdicts = auditor.audit(&forged, &submissions);
+        assert!(verdicts[0].is_valid());
+        assert_eq!(
+            verdicts[1].failures(),
+            &[AuditFailure::InvalidEpochProof(AssetId::NATIVE)]
+        );
+
+        // The epoch proofs are bound to the address sets the organizations committed to
+        let mut address_set_roots = statement.address_set_roots().clone();
+        address_set_roots.insert("org2".to_owned(), [9; 32]);
+        address_set_roots.remove("org1");
+        let resets = EpochStatement::new(
+            0,
+            statement.members().to_vec(),
+            network.blockchain().delta_commitments(0),
+            address_set_roots,
+            network.blockchain().external_flows(0),
+        );
+        let verdicts = auditor.audit(&resets, &submissions);
+        assert_eq!(verdicts[0].failures(), &[AuditFailure::NoAddressSet]);
+        assert_eq!(
+            verdicts[1].failures(),
+            &[AuditFailure::InvalidEpochProof(AssetId::NATIVE)]
+        );
+    }
+}
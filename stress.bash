@@ -1,4 +1,13 @@
 while :
 do
-    cargo run --example ot --release
+    rm -f stress_board.json
+    cargo run --bin ducat --release -- init --state stress_state.json --keys stress_keys.json &&
+        cargo run --bin ducat --release -- simulate --state stress_state.json --transactions 100 &&
+        cargo run --bin ducat --release -- prove --state stress_state.json --board stress_board.json --validator &&
+        cargo run --bin ducat --release -- verify --keys stress_keys.json --board stress_board.json &&
+        cargo run --bin ducat --release -- end-epoch --state stress_state.json &&
+        cargo run --bin ducat --release -- simulate --state stress_state.json --transactions 100 &&
+        cargo run --bin ducat --release -- prove --state stress_state.json --board stress_board.json --validator &&
+        cargo run --bin ducat --release -- verify --keys stress_keys.json --board stress_board.json ||
+        break
 done